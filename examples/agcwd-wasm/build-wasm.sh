@@ -2,4 +2,5 @@
 
 set -eux
 
-wasm-pack build --release -t web
+# Enables the SIMD implementations of agcwd.
+RUSTFLAGS="-C target-feature=+simd128" wasm-pack build --release -t web
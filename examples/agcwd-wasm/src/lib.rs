@@ -16,6 +16,9 @@ pub fn enhance_rgba_image(pixels: &mut [u8], options: &JsValue) -> Result<(), Js
     let options = agcwd::AgcwdOptions {
         alpha: options.alpha,
         fusion: options.fusion,
+        // Uses the vectorized path to apply curves (see `build-wasm.sh`).
+        exact_hue: true,
+        ..Default::default()
     };
     agcwd::Agcwd::with_options(options).enhance_rgba_image(pixels);
     Ok(())
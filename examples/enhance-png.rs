@@ -13,40 +13,66 @@ struct Opt {
 
     #[structopt(long, default_value = "0.0")]
     fusion: f32,
+
+    /// Selects `alpha` from the histogram of the image (`--alpha` is ignored).
+    #[structopt(long)]
+    auto_alpha: bool,
+
+    /// Selects `fusion` from the histogram of the image (`--fusion` is ignored).
+    #[structopt(long)]
+    auto_fusion: bool,
 }
 
 fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
 
     let file = std::fs::File::open(&opt.image_path)?;
-    let decoder = png::Decoder::new(std::io::BufReader::new(file));
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    // Expands indexed and low bit depth images into 8-bit ones.
+    decoder.set_transformations(png::Transformations::EXPAND);
     let mut reader = decoder.read_info()?;
 
     let mut buf = vec![0; reader.output_buffer_size()];
     let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
     println!("Image resolution: {}x{}", info.width, info.height);
     println!("Image bit depth: {:?}", info.bit_depth);
     println!("Image color type: {:?}", info.color_type);
-    assert_eq!(info.bit_depth, png::BitDepth::Eight);
 
     let options = agcwd::AgcwdOptions {
         alpha: opt.alpha,
         fusion: opt.fusion,
+        auto_alpha: opt.auto_alpha,
+        auto_fusion: opt.auto_fusion,
+        ..Default::default()
     };
     let agcwd = agcwd::Agcwd::with_options(options);
     let start = std::time::Instant::now();
-    match reader.info().color_type {
-        png::ColorType::Rgb => {
-            agcwd.enhance_rgb_image(&mut buf);
-        }
-        png::ColorType::Rgba => {
-            agcwd.enhance_rgba_image(&mut buf);
-        }
-        ty => {
-            panic!("Unsupported color type: {:?}", ty);
+    let format = match info.color_type {
+        png::ColorType::Grayscale => agcwd::PixelFormat::Gray,
+        png::ColorType::GrayscaleAlpha => agcwd::PixelFormat::GrayAlpha,
+        png::ColorType::Rgb => agcwd::PixelFormat::Rgb,
+        png::ColorType::Rgba => agcwd::PixelFormat::Rgba,
+        ty => anyhow::bail!("Unsupported color type: {:?}", ty),
+    };
+    let (alpha, fusion) = if info.bit_depth == png::BitDepth::Sixteen {
+        let mut samples = buf
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect::<Vec<_>>();
+        let curve = agcwd.compute_curve16(&samples, format);
+        curve.apply_image(&mut samples, format);
+        for (b, v) in buf.chunks_exact_mut(2).zip(samples) {
+            b.copy_from_slice(&v.to_be_bytes());
         }
-    }
+        (curve.alpha(), curve.fusion())
+    } else {
+        let curve = agcwd.compute_curve(&buf, format);
+        curve.apply_image(&mut buf, format);
+        (curve.alpha(), curve.fusion())
+    };
     println!("Elapsed: {:?}", start.elapsed());
+    println!("Alpha: {}, Fusion: {}", alpha, fusion);
 
     let mut encoder = png::Encoder::new(
         std::io::BufWriter::new(std::fs::File::create(&opt.output_path)?),
@@ -54,9 +80,10 @@ fn main() -> anyhow::Result<()> {
         info.height,
     );
     encoder.set_color(info.color_type);
-    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_depth(info.bit_depth);
     let mut writer = encoder.write_header()?;
     writer.write_image_data(&buf)?;
+    writer.finish()?;
 
     println!("Output path: {:?}", opt.output_path);
 
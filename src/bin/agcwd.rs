@@ -0,0 +1,567 @@
+//! Command-line tool to enhance the contrast of PNG images.
+//!
+//! All PNG color types and bit depths are supported, and the output images keep those of the input images.
+//!
+//! Exit codes:
+//! - `0`: All the images were enhanced.
+//! - `1`: Some images could not be enhanced (the other images are still processed).
+//! - `2`: The arguments are invalid.
+use agcwd::{Agcwd, AgcwdOptions, AgcwdVariant, IntensityModel, PixelFormat};
+use anyhow::Context;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, StructOpt)]
+#[structopt(about = "Enhances the contrast of PNG images using the AGCWD algorithm")]
+struct Opt {
+    /// PNG files or directories containing PNG files.
+    #[structopt(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Template of the output paths.
+    ///
+    /// `{dir}`, `{name}`, `{stem}` and `{ext}` are replaced with the directory, file name,
+    /// file name without the extension, and extension of an input file respectively.
+    /// Files in an input directory that are the outputs of other files in it are skipped.
+    #[structopt(short, long, default_value = "{dir}/{stem}-enhanced.{ext}")]
+    output: String,
+
+    /// Overwrites existing output files.
+    #[structopt(long)]
+    overwrite: bool,
+
+    /// Suppresses the progress messages.
+    #[structopt(short, long)]
+    quiet: bool,
+
+    /// Parameter to adjust the shape of the weighting distribution.
+    #[structopt(long, default_value = "0.5", allow_hyphen_values = true)]
+    alpha: f32,
+
+    /// Fusion rate of the original image and the enhanced image (from 0.0 to 1.0).
+    #[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+    fusion: f32,
+
+    /// Selects `alpha` from the histogram of each image (`--alpha` is ignored).
+    #[structopt(long)]
+    auto_alpha: bool,
+
+    /// Selects `fusion` from the histogram of each image (`--fusion` is ignored).
+    #[structopt(long)]
+    auto_fusion: bool,
+
+    /// Number of histogram bins used to enhance 16-bit images.
+    #[structopt(long, default_value = "4096")]
+    histogram_bins: usize,
+
+    /// Color model used to derive the intensity of a pixel
+    /// (`hsv-value`, `bt601-luma`, `bt709-luma`, `hsl-lightness` or `cielab-lightness`).
+    #[structopt(long, default_value = "hsv-value", parse(try_from_str = parse_intensity_model))]
+    intensity_model: IntensityModel,
+
+    /// Scales the color components of a pixel exactly instead of converting them to and from the color model.
+    #[structopt(long)]
+    exact_hue: bool,
+
+    /// Variant of the algorithm (`original` or `improved`).
+    #[structopt(long, default_value = "original", parse(try_from_str = parse_variant))]
+    variant: AgcwdVariant,
+}
+
+impl Opt {
+    fn agcwd_options(&self) -> AgcwdOptions {
+        AgcwdOptions {
+            alpha: self.alpha,
+            fusion: self.fusion,
+            auto_alpha: self.auto_alpha,
+            auto_fusion: self.auto_fusion,
+            histogram_bins: self.histogram_bins,
+            intensity_model: self.intensity_model,
+            exact_hue: self.exact_hue,
+            variant: self.variant,
+        }
+    }
+}
+
+fn parse_intensity_model(s: &str) -> anyhow::Result<IntensityModel> {
+    match s {
+        "hsv-value" => Ok(IntensityModel::HsvValue),
+        "bt601-luma" => Ok(IntensityModel::Bt601Luma),
+        "bt709-luma" => Ok(IntensityModel::Bt709Luma),
+        "hsl-lightness" => Ok(IntensityModel::HslLightness),
+        "cielab-lightness" => Ok(IntensityModel::CielabLightness),
+        _ => anyhow::bail!("unknown intensity model: {s:?}"),
+    }
+}
+
+fn parse_variant(s: &str) -> anyhow::Result<AgcwdVariant> {
+    match s {
+        "original" => Ok(AgcwdVariant::Original),
+        "improved" => Ok(AgcwdVariant::Improved),
+        _ => anyhow::bail!("unknown variant: {s:?}"),
+    }
+}
+
+fn main() {
+    let opt = match Opt::from_iter_safe(std::env::args_os()) {
+        Ok(opt) => opt,
+        Err(e) if e.use_stderr() => {
+            eprintln!("{e}");
+            std::process::exit(EXIT_USAGE);
+        }
+        Err(e) => e.exit(), // `--help` or `--version`.
+    };
+    let agcwd = match Agcwd::try_with_options(opt.agcwd_options()) {
+        Ok(agcwd) => agcwd,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    let mut failed = false;
+    for input in &opt.inputs {
+        let files = match collect_files(input, &opt.output) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("error: {e:#}");
+                failed = true;
+                continue;
+            }
+        };
+        for file in files {
+            let result = output_path(&opt.output, &file)
+                .and_then(|output| enhance_file(&agcwd, &file, &output, opt.overwrite));
+            match result {
+                Ok(output) if !opt.quiet => println!("{} -> {}", file.display(), output.display()),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("error: {}: {e:#}", file.display());
+                    failed = true;
+                }
+            }
+        }
+    }
+    if failed {
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+/// Returns the PNG files in a directory (not recursively), or the path itself if it is not a directory.
+///
+/// The files that are the outputs of other files in the directory (e.g., those written by a previous run) are skipped.
+fn collect_files(path: &Path, template: &str) -> anyhow::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let entries = std::fs::read_dir(path)
+        .with_context(|| format!("failed to read directory {}", path.display()))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if is_png && path.is_file() {
+            files.push(path);
+        }
+    }
+    let outputs = files
+        .iter()
+        .filter_map(|file| output_path(template, file).ok())
+        .map(|output| normalize(&output))
+        .collect::<HashSet<_>>();
+    files.retain(|file| !outputs.contains(&normalize(file)));
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the path having the canonicalized parent directory, so that different spellings of a path compare equal.
+///
+/// The file itself need not exist.
+fn normalize(path: &Path) -> PathBuf {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    match path.file_name() {
+        Some(name) => dir.join(name),
+        None => dir,
+    }
+}
+
+fn output_path(template: &str, input: &Path) -> anyhow::Result<PathBuf> {
+    let part = |s: Option<&std::ffi::OsStr>| -> String {
+        s.map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let dir = input
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let output = template
+        .replace("{dir}", &dir.to_string_lossy())
+        .replace("{name}", &part(input.file_name()))
+        .replace("{stem}", &part(input.file_stem()))
+        .replace("{ext}", &part(input.extension()));
+    let output = PathBuf::from(output);
+    anyhow::ensure!(
+        normalize(&output) != normalize(input),
+        "output path is the same as the input path: {}",
+        output.display()
+    );
+    Ok(output)
+}
+
+fn enhance_file(
+    agcwd: &Agcwd,
+    input: &Path,
+    output: &Path,
+    overwrite: bool,
+) -> anyhow::Result<PathBuf> {
+    anyhow::ensure!(
+        overwrite || !output.exists(),
+        "output file already exists (use --overwrite): {}",
+        output.display()
+    );
+
+    let file = std::fs::File::open(input).context("failed to open the input file")?;
+    let decoder = png::Decoder::new(std::io::BufReader::new(file));
+    let mut reader = decoder
+        .read_info()
+        .context("failed to decode the input file")?;
+    let mut palette = reader.info().palette.as_ref().map(|p| p.to_vec());
+    let trns = reader.info().trns.as_ref().map(|t| t.to_vec());
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .context("failed to decode the input file")?;
+    buf.truncate(info.buffer_size());
+
+    let trns = enhance_png(
+        agcwd,
+        &info,
+        &mut buf,
+        palette.as_deref_mut(),
+        trns.as_deref(),
+    )?;
+
+    if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+    }
+    let file = std::fs::File::create(output)
+        .with_context(|| format!("failed to create {}", output.display()))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), info.width, info.height);
+    encoder.set_color(info.color_type);
+    encoder.set_depth(info.bit_depth);
+    if let Some(palette) = palette {
+        encoder.set_palette(palette);
+    }
+    if let Some(trns) = trns {
+        encoder.set_trns(trns);
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&buf)?;
+    writer.finish()?;
+    Ok(output.to_path_buf())
+}
+
+/// Enhances the decoded pixels of a PNG image keeping its color type and bit depth.
+///
+/// The palette of an indexed image is enhanced instead of the pixels
+/// (the curve is computed from the colors of the pixels).
+///
+/// Returns the `tRNS` chunk to be written, whose transparent color (if any) is mapped by the same curve as the pixels.
+fn enhance_png(
+    agcwd: &Agcwd,
+    info: &png::OutputInfo,
+    buf: &mut [u8],
+    palette: Option<&mut [u8]>,
+    trns: Option<&[u8]>,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let format = match info.color_type {
+        png::ColorType::Grayscale => PixelFormat::Gray,
+        png::ColorType::GrayscaleAlpha => PixelFormat::GrayAlpha,
+        png::ColorType::Rgb => PixelFormat::Rgb,
+        png::ColorType::Rgba => PixelFormat::Rgba,
+        png::ColorType::Indexed => {
+            let palette = palette.context("missing palette")?;
+            let mut colors = Vec::new();
+            for i in unpack_samples(info, buf) {
+                let i = usize::from(i) * 3;
+                let color = palette
+                    .get(i..i + 3)
+                    .context("palette index out of range")?;
+                colors.extend_from_slice(color);
+            }
+            let curve = agcwd.compute_rgb_curve(&colors);
+            curve.apply_rgb_image(palette);
+            // The alpha values of the palette entries are kept.
+            return Ok(trns.map(<[u8]>::to_vec));
+        }
+    };
+    // Only grayscale and RGB images can have a transparent color.
+    let trns = trns.filter(|_| format.alpha_offset().is_none());
+
+    // The decoder stores a sample of the transparent color per byte if the bit depth is less than 16,
+    // while the encoder writes the chunk as is, which must hold 16-bit samples.
+    let key = match info.bit_depth {
+        png::BitDepth::Eight => {
+            let curve = agcwd.compute_curve(buf, format);
+            curve.apply_image(buf, format);
+            trns.map(|trns| {
+                let mut key = trns.to_vec();
+                curve.apply_image(&mut key, format);
+                key.into_iter().map(u16::from).collect::<Vec<_>>()
+            })
+        }
+        png::BitDepth::Sixteen => {
+            let mut samples = buf
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .collect::<Vec<_>>();
+            let curve = agcwd.compute_curve16(&samples, format);
+            curve.apply_image(&mut samples, format);
+            for (b, v) in buf.chunks_exact_mut(2).zip(samples) {
+                b.copy_from_slice(&v.to_be_bytes());
+            }
+            trns.map(|trns| {
+                let mut key = trns
+                    .chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                    .collect::<Vec<_>>();
+                curve.apply_image(&mut key, format);
+                key
+            })
+        }
+        depth => {
+            // Only grayscale images can have bit depths less than 8.
+            let max = (1u16 << depth as u8) - 1;
+            let to_u8 = |v: u8| (u16::from(v) * 255 / max) as u8;
+            let from_u8 = |v: u8| ((u16::from(v) * max + 127) / 255) as u8;
+            let mut samples = unpack_samples(info, buf).map(to_u8).collect::<Vec<_>>();
+            let curve = agcwd.compute_gray_curve(&samples);
+            curve.apply_gray_image(&mut samples);
+            for v in &mut samples {
+                *v = from_u8(*v);
+            }
+            pack_samples(info, buf, &samples);
+            trns.map(|trns| {
+                trns.iter()
+                    .map(|&v| u16::from(from_u8(curve.get(to_u8(v)))))
+                    .collect()
+            })
+        }
+    };
+    Ok(key.map(|key| key.into_iter().flat_map(u16::to_be_bytes).collect()))
+}
+
+/// Returns the samples of an image having one sample per pixel (i.e., grayscale or indexed).
+fn unpack_samples<'a>(info: &png::OutputInfo, buf: &'a [u8]) -> impl 'a + Iterator<Item = u8> {
+    let depth = info.bit_depth as usize;
+    let (width, line_size) = (info.width as usize, info.line_size);
+    let mask = ((1u16 << depth) - 1) as u8;
+    buf.chunks(line_size).flat_map(move |row| {
+        (0..width).map(move |x| {
+            let bit = x * depth;
+            let shift = 8 - depth - bit % 8;
+            (row[bit / 8] >> shift) & mask
+        })
+    })
+}
+
+/// Stores the samples returned by [`unpack_samples()`] back into `buf`.
+fn pack_samples(info: &png::OutputInfo, buf: &mut [u8], samples: &[u8]) {
+    let depth = info.bit_depth as usize;
+    let (width, line_size) = (info.width as usize, info.line_size);
+    let mask = ((1u16 << depth) - 1) as u8;
+    for (row, samples) in buf.chunks_mut(line_size).zip(samples.chunks(width)) {
+        for (x, &v) in samples.iter().enumerate() {
+            let bit = x * depth;
+            let shift = 8 - depth - bit % 8;
+            row[bit / 8] = row[bit / 8] & !(mask << shift) | (v << shift);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_info(width: u32, height: u32, bit_depth: png::BitDepth) -> png::OutputInfo {
+        png::OutputInfo {
+            width,
+            height,
+            color_type: png::ColorType::Grayscale,
+            bit_depth,
+            line_size: (width as usize * bit_depth as usize).div_ceil(8),
+        }
+    }
+
+    #[test]
+    fn samples_round_trip() {
+        for depth in [
+            png::BitDepth::One,
+            png::BitDepth::Two,
+            png::BitDepth::Four,
+            png::BitDepth::Eight,
+        ] {
+            // Rows of 5 samples do not end at byte boundaries unless the depth is 8.
+            let info = gray_info(5, 3, depth);
+            let max = (1u16 << depth as u8) - 1;
+            let samples = (0..15)
+                .map(|i| (i * 7 % (max + 1)) as u8)
+                .collect::<Vec<_>>();
+            let mut buf = vec![0; info.line_size * 3];
+            pack_samples(&info, &mut buf, &samples);
+            assert_eq!(unpack_samples(&info, &buf).collect::<Vec<_>>(), samples);
+        }
+
+        // The most significant bits hold the first sample, and the padding bits are left untouched.
+        let info = gray_info(5, 1, png::BitDepth::One);
+        let mut buf = [0b0000_0111];
+        pack_samples(&info, &mut buf, &[1, 0, 1, 1, 0]);
+        assert_eq!(buf, [0b1011_0111]);
+    }
+
+    #[test]
+    fn enhance_png_keeps_uniform_images() {
+        // A uniform histogram gives the identity curve, so the decoded samples must be restored exactly.
+        let agcwd = Agcwd::new();
+        let mut buf = (0..=255).collect::<Vec<u8>>();
+        enhance_png(
+            &agcwd,
+            &gray_info(16, 16, png::BitDepth::Eight),
+            &mut buf,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(buf, (0..=255).collect::<Vec<u8>>());
+
+        let ramp = (0..=u16::MAX)
+            .flat_map(u16::to_be_bytes)
+            .collect::<Vec<_>>();
+        let mut buf = ramp.clone();
+        enhance_png(
+            &agcwd,
+            &gray_info(256, 256, png::BitDepth::Sixteen),
+            &mut buf,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(buf, ramp);
+    }
+
+    #[test]
+    fn transparent_color_is_mapped_by_curve() {
+        let dir = std::env::temp_dir().join(format!("agcwd-trns-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("gray.png"), dir.join("gray-enhanced.png"));
+
+        let pixels = [0, 10, 20, 30, 40, 50, 60, 70, 80];
+        let file = std::fs::File::create(&input).unwrap();
+        let mut encoder = png::Encoder::new(file, 3, 3);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_trns(vec![0, 20]);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels).unwrap();
+        writer.finish().unwrap();
+
+        enhance_file(&Agcwd::new(), &input, &output, false).unwrap();
+
+        // The chunk holds a 16-bit sample.
+        let bytes = std::fs::read(&output).unwrap();
+        let i = bytes.windows(4).position(|w| w == b"tRNS").unwrap();
+        assert_eq!(bytes[i - 4..i], [0, 0, 0, 2]);
+
+        let decoder = png::Decoder::new(std::fs::File::open(&output).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let key = reader.info().trns.as_ref().unwrap().to_vec();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_ne!(buf[2], 20);
+        assert_eq!(key, [buf[2]]);
+        assert_eq!(buf.iter().filter(|&&v| v == key[0]).count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn transparent_color_of_16_bit_rgb_image_is_mapped_by_curve() {
+        let info = png::OutputInfo {
+            width: 3,
+            height: 1,
+            color_type: png::ColorType::Rgb,
+            bit_depth: png::BitDepth::Sixteen,
+            line_size: 18,
+        };
+        let samples = [1000u16, 2000, 3000, 9000, 8000, 7000, 30000, 20000, 10000];
+        let mut buf = samples
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+        let trns = buf[6..12].to_vec();
+        let key = enhance_png(&Agcwd::new(), &info, &mut buf, None, Some(&trns))
+            .unwrap()
+            .unwrap();
+        assert_ne!(key, trns);
+        assert_eq!(key, buf[6..12]);
+    }
+
+    #[test]
+    fn output_path_works() {
+        let input = Path::new("images/photo.png");
+        let output = |template| output_path(template, input).unwrap();
+        assert_eq!(
+            output("{dir}/{stem}-enhanced.{ext}"),
+            Path::new("images/photo-enhanced.png")
+        );
+        assert_eq!(output("out/{name}"), Path::new("out/photo.png"));
+        assert_eq!(
+            output_path("{dir}/{stem}-2.{ext}", Path::new("photo.png")).unwrap(),
+            Path::new("./photo-2.png")
+        );
+
+        // The same file spelled differently (`src` exists, so it can be canonicalized).
+        assert!(output_path("{dir}/{name}", input).is_err());
+        assert!(output_path("{dir}/{name}", Path::new("photo.png")).is_err());
+        assert!(output_path("src/../src/{stem}.{ext}", Path::new("src/photo.png")).is_err());
+    }
+
+    #[test]
+    fn collect_files_skips_outputs() {
+        let dir = std::env::temp_dir().join(format!("agcwd-collect-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "a-enhanced.png", "b.PNG", "c.jpg"] {
+            std::fs::write(dir.join(name), []).unwrap();
+        }
+
+        let files = collect_files(&dir, "{dir}/{stem}-enhanced.{ext}").unwrap();
+        assert_eq!(files, [dir.join("a.png"), dir.join("b.PNG")]);
+        let files = collect_files(&dir, "out/{name}").unwrap();
+        assert_eq!(
+            files,
+            [
+                dir.join("a-enhanced.png"),
+                dir.join("a.png"),
+                dir.join("b.PNG")
+            ]
+        );
+        let file = dir.join("a-enhanced.png");
+        assert_eq!(
+            collect_files(&file, "{dir}/{stem}-enhanced.{ext}").unwrap(),
+            [file]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,5 @@
+use crate::{simd, PixelFormat};
+
 pub fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
     let r = usize::from(r);
     let g = usize::from(g);
@@ -6,7 +8,7 @@ pub fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
     let min = std::cmp::min(r, std::cmp::min(g, b));
     let n = max - min;
 
-    let s = if max == 0 { 0 } else { n * 255 / max };
+    let s = (n * 255).checked_div(max).unwrap_or(0);
     let v = max;
     let h = if n == 0 {
         0
@@ -68,6 +70,199 @@ pub fn hsv_to_rgb(h: u8, s: u8, v: u8) -> (u8, u8, u8) {
     (r as u8, g as u8, b as u8)
 }
 
+/// Luma coefficients (Kr and Kb) defined by ITU-R BT.601.
+pub const BT601: (f32, f32) = (0.299, 0.114);
+
+/// Luma coefficients (Kr and Kb) defined by ITU-R BT.709.
+pub const BT709: (f32, f32) = (0.2126, 0.0722);
+
+/// Converts an RGB pixel to full-range Y'CbCr (Y' is in `0.0..=255.0`).
+pub fn rgb_to_ycbcr(r: u8, g: u8, b: u8, (kr, kb): (f32, f32)) -> (f32, f32, f32) {
+    let r = f32::from(r);
+    let g = f32::from(g);
+    let b = f32::from(b);
+    let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+    let cb = (b - y) / (2.0 * (1.0 - kb));
+    let cr = (r - y) / (2.0 * (1.0 - kr));
+    (y, cb, cr)
+}
+
+pub fn ycbcr_to_rgb(y: f32, cb: f32, cr: f32, (kr, kb): (f32, f32)) -> (u8, u8, u8) {
+    let r = y + 2.0 * (1.0 - kr) * cr;
+    let b = y + 2.0 * (1.0 - kb) * cb;
+    let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+/// Converts an RGB pixel to HSL (H is in `0.0..6.0`, S is in `0.0..=1.0` and L is in `0.0..=255.0`).
+pub fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let max = std::cmp::max(r, std::cmp::max(g, b));
+    let min = std::cmp::min(r, std::cmp::min(g, b));
+    let (r, g, b) = (f32::from(r), f32::from(g), f32::from(b));
+    let c = f32::from(max - min);
+    let l = (f32::from(max) + f32::from(min)) / 2.0;
+    if c == 0.0 {
+        return (0.0, 0.0, l);
+    }
+
+    let s = c / (255.0 - (2.0 * l - 255.0).abs());
+    let h = if f32::from(max) == r {
+        ((g - b) / c).rem_euclid(6.0)
+    } else if f32::from(max) == g {
+        (b - r) / c + 2.0
+    } else {
+        (r - g) / c + 4.0
+    };
+    (h, s, l)
+}
+
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+    let c = (255.0 - (2.0 * l - 255.0).abs()) * s;
+    let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    (to_u8(r + m), to_u8(g + m), to_u8(b + m))
+}
+
+const D65_WHITE: (f32, f32, f32) = (0.950_47, 1.0, 1.088_83);
+
+/// Converts an sRGB pixel to CIELAB (L* is in `0.0..=100.0`).
+pub fn rgb_to_lab(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    fn linearize(c: u8) -> f32 {
+        let c = f32::from(c) / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+    fn f(t: f32) -> f32 {
+        const DELTA: f32 = 6.0 / 29.0;
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    }
+
+    let (r, g, b) = (linearize(r), linearize(g), linearize(b));
+    let x = 0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = 0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b;
+
+    let fx = f(x / D65_WHITE.0);
+    let fy = f(y / D65_WHITE.1);
+    let fz = f(z / D65_WHITE.2);
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+pub fn lab_to_rgb(l: f32, a: f32, b: f32) -> (u8, u8, u8) {
+    fn f_inv(t: f32) -> f32 {
+        const DELTA: f32 = 6.0 / 29.0;
+        if t > DELTA {
+            t * t * t
+        } else {
+            3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+        }
+    }
+    fn delinearize(c: f32) -> u8 {
+        let c = if c <= 0.003_130_8 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        to_u8(c * 255.0)
+    }
+
+    let fy = (l + 16.0) / 116.0;
+    let x = D65_WHITE.0 * f_inv(fy + a / 500.0);
+    let y = D65_WHITE.1 * f_inv(fy);
+    let z = D65_WHITE.2 * f_inv(fy - b / 200.0);
+
+    let r = 3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z;
+    let g = -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z;
+    let b = 0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z;
+    (delinearize(r), delinearize(g), delinearize(b))
+}
+
+fn to_u8(x: f32) -> u8 {
+    x.round().clamp(0.0, 255.0) as u8
+}
+
+/// Table of fixed-point gains to scale RGB pixels by `curve[i] / i` (where `i` is the intensity of a pixel).
+///
+/// Scaling a component needs only a multiplication and a shift, and the result is identical to
+/// `round(c * curve[i] / i)` (rounding half up and clipped to `255`).
+#[derive(Debug)]
+pub struct GainTable {
+    pub(crate) gains: [u32; 256],
+    pub(crate) black: u8,
+}
+
+impl GainTable {
+    pub const SHIFT: u32 = 17;
+
+    pub fn new(curve: &[u8; 256]) -> Self {
+        let mut gains = [0; 256];
+        for (i, g) in gains.iter_mut().enumerate().skip(1) {
+            // Rounding up the gains makes the results exact (`2^17` is greater than `2 * 255 * 255`).
+            *g = (u32::from(curve[i]) << Self::SHIFT).div_ceil(i as u32);
+        }
+        Self {
+            gains,
+            black: curve[0],
+        }
+    }
+
+    /// Scales an RGB pixel having the intensity `i`.
+    ///
+    /// If `i` is `0`, each component is raised to `curve[0]` instead.
+    pub fn scale_rgb(&self, r: u8, g: u8, b: u8, i: u8) -> (u8, u8, u8) {
+        if i == 0 {
+            return (r.max(self.black), g.max(self.black), b.max(self.black));
+        }
+        let gain = u64::from(self.gains[usize::from(i)]);
+        let scale = |c: u8| {
+            ((u64::from(c) * gain + (1 << (Self::SHIFT - 1))) >> Self::SHIFT).min(255) as u8
+        };
+        (scale(r), scale(g), scale(b))
+    }
+
+    /// Scales the RGB pixels in `pixels` by the gains indexed by their HSV values.
+    ///
+    /// The results are the same as those of [`GainTable::scale_rgb()`], but the scaling is vectorized.
+    pub fn scale_by_value(&self, pixels: &mut [u8], format: PixelFormat) {
+        const BLOCK_PIXELS: usize = 1024;
+        let channels = format.channels();
+        let mut values = [0; BLOCK_PIXELS];
+        for block in pixels.chunks_mut(BLOCK_PIXELS * channels) {
+            let values = &mut values[..block.len() / channels];
+            simd::max3(block, format, values);
+            simd::scale_by_value(block, format, values, &self.gains, self.black);
+        }
+    }
+}
+
+/// Scales an RGB pixel so that its HSV value becomes `v_new` while keeping its hue and saturation.
+///
+/// `v` must be the current HSV value (i.e., the maximum of `r`, `g` and `b`).
+pub fn scale_rgb16(r: u16, g: u16, b: u16, v: u16, v_new: u16) -> (u16, u16, u16) {
+    if v == 0 {
+        return (v_new, v_new, v_new);
+    }
+    let v = u32::from(v);
+    let v_new = u32::from(v_new);
+    let scale = |c: u16| ((u32::from(c) * v_new + v / 2) / v) as u16;
+    (scale(r), scale(g), scale(b))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +282,35 @@ mod tests {
             assert!((i32::from(b) - i32::from(i.2)).abs() <= 2);
         }
     }
+
+    #[test]
+    fn ycbcr_hsl_and_lab_round_trips_work() {
+        let inputs = [(255, 0, 0), (10, 30, 200), (222, 222, 222), (0, 0, 0)];
+        for i in inputs {
+            for k in [BT601, BT709] {
+                let (y, cb, cr) = rgb_to_ycbcr(i.0, i.1, i.2, k);
+                assert_eq!(ycbcr_to_rgb(y, cb, cr, k), i);
+            }
+
+            let (h, s, l) = rgb_to_hsl(i.0, i.1, i.2);
+            assert_eq!(hsl_to_rgb(h, s, l), i);
+
+            let (l, a, b) = rgb_to_lab(i.0, i.1, i.2);
+            assert_eq!(lab_to_rgb(l, a, b), i);
+        }
+    }
+
+    #[test]
+    fn gain_table_is_exact() {
+        for t in 0..=255 {
+            let table = GainTable::new(&[t; 256]);
+            for i in 1..=255 {
+                for c in 0..=255 {
+                    let expected = ((u32::from(c) * u32::from(t) + u32::from(i) / 2) / u32::from(i))
+                        .min(255) as u8;
+                    assert_eq!(table.scale_rgb(c, c, c, i).0, expected);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,59 @@
+use crate::{Agcwd, AgcwdError};
+use image::DynamicImage;
+
+impl Agcwd {
+    /// Enhances the contrast of an image of the [image](https://crates.io/crates/image) crate.
+    ///
+    /// The pixels are processed by the method corresponding to the color type of the image
+    /// (e.g., [`Agcwd::enhance_rgb16_image()`] for [`DynamicImage::ImageRgb16`]),
+    /// so the color type is preserved.
+    ///
+    /// Returns [`AgcwdError::UnsupportedColorType`] if the color type is unknown to this crate
+    /// (the variants of [`DynamicImage`] may be added in future versions of the image crate).
+    ///
+    /// This method is available only when the `image` feature is enabled.
+    pub fn enhance_dynamic_image(&self, image: &mut DynamicImage) -> Result<(), AgcwdError> {
+        match image {
+            DynamicImage::ImageLuma8(image) => self.enhance_gray_image(image),
+            DynamicImage::ImageLumaA8(image) => self.enhance_gray_alpha_image(image),
+            DynamicImage::ImageRgb8(image) => self.enhance_rgb_image(image),
+            DynamicImage::ImageRgba8(image) => self.enhance_rgba_image(image),
+            DynamicImage::ImageLuma16(image) => self.enhance_gray16_image(image),
+            DynamicImage::ImageLumaA16(image) => self.enhance_gray_alpha16_image(image),
+            DynamicImage::ImageRgb16(image) => self.enhance_rgb16_image(image),
+            DynamicImage::ImageRgba16(image) => self.enhance_rgba16_image(image),
+            DynamicImage::ImageRgb32F(image) => self.enhance_rgb_f32_image(image),
+            DynamicImage::ImageRgba32F(image) => self.enhance_rgba_f32_image(image),
+            _ => return Err(AgcwdError::UnsupportedColorType),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma, Rgb, Rgb32FImage, RgbImage};
+
+    #[test]
+    fn enhance_dynamic_image_works() {
+        let agcwd = Agcwd::new();
+
+        let rgb = RgbImage::from_fn(4, 2, |x, y| Rgb([(x * 40) as u8, (y * 50) as u8, 10]));
+        let mut expected = rgb.clone().into_raw();
+        agcwd.enhance_rgb_image(&mut expected);
+        let mut image = DynamicImage::ImageRgb8(rgb);
+        agcwd.enhance_dynamic_image(&mut image).unwrap();
+        assert_eq!(image.as_rgb8().unwrap().as_raw(), &expected);
+
+        let gray = ImageBuffer::<Luma<u16>, _>::from_raw(2, 1, vec![1000, 2000]).unwrap();
+        let mut image = DynamicImage::ImageLuma16(gray.clone());
+        agcwd.enhance_dynamic_image(&mut image).unwrap();
+        assert_ne!(image.as_luma16().unwrap(), &gray);
+
+        let rgb = Rgb32FImage::from_fn(2, 2, |x, y| Rgb([x as f32 * 0.2, y as f32 * 0.3, 0.1]));
+        let mut image = DynamicImage::ImageRgb32F(rgb.clone());
+        agcwd.enhance_dynamic_image(&mut image).unwrap();
+        assert_ne!(image.as_rgb32f().unwrap(), &rgb);
+    }
+}
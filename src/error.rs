@@ -0,0 +1,72 @@
+use crate::PixelFormat;
+
+/// Errors returned by the fallible (`try_`) methods of this crate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgcwdError {
+    /// The length of a pixel buffer is not a multiple of the number of channels of its pixel format.
+    InvalidLength {
+        /// Length of the buffer.
+        len: usize,
+
+        /// Pixel format of the buffer.
+        format: PixelFormat,
+    },
+
+    /// An image has no pixels.
+    EmptyImage,
+
+    /// The stride of an image is smaller than a row of pixels.
+    InvalidStride {
+        /// Stride of the image in bytes.
+        stride: usize,
+
+        /// Length of a row of pixels in bytes.
+        row_len: usize,
+    },
+
+    /// A buffer is too short to hold an image of the given size.
+    BufferTooShort {
+        /// Length of the buffer.
+        len: usize,
+
+        /// Length required to hold the image.
+        required: usize,
+    },
+
+    /// The color type of an image is not supported.
+    UnsupportedColorType,
+
+    /// An option has a value out of its valid range.
+    InvalidOption {
+        /// Name of the option (e.g., `"alpha"`).
+        name: &'static str,
+
+        /// Value of the option.
+        value: f32,
+    },
+}
+
+impl std::fmt::Display for AgcwdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLength { len, format } => write!(
+                f,
+                "buffer length {len} is not a multiple of the number of channels of {format:?} ({})",
+                format.channels()
+            ),
+            Self::EmptyImage => write!(f, "image has no pixels"),
+            Self::InvalidStride { stride, row_len } => {
+                write!(f, "stride is too small: stride={stride}, row_len={row_len}")
+            }
+            Self::BufferTooShort { len, required } => {
+                write!(f, "buffer is too short: len={len}, required={required}")
+            }
+            Self::UnsupportedColorType => write!(f, "unsupported color type"),
+            Self::InvalidOption { name, value } => {
+                write!(f, "option `{name}` has an invalid value: {value}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AgcwdError {}
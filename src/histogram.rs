@@ -0,0 +1,200 @@
+use crate::{Image, ImageView, IntensityModel, PixelFormat};
+
+/// Histogram of the intensities of an 8-bit image.
+///
+/// The intensities are derived by an [`IntensityModel`] in the same way as [`Agcwd`](crate::Agcwd) does.
+/// This provides statistics useful to decide whether an image needs enhancement and to log its exposure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<usize>,
+    total: usize,
+}
+
+impl Histogram {
+    /// Computes the histogram of an image having the given pixel format.
+    pub fn new(pixels: &[u8], format: PixelFormat, model: IntensityModel) -> Self {
+        Self::from_counts(Image::new(pixels, format).intensity_histogram(model))
+    }
+
+    /// Computes the histogram of an image view.
+    pub fn from_image_view<B: AsRef<[u8]>>(image: &ImageView<B>, model: IntensityModel) -> Self {
+        Self::from_counts(image.as_image().intensity_histogram(model))
+    }
+
+    fn from_counts(counts: Vec<usize>) -> Self {
+        let total = counts.iter().sum();
+        Self { counts, total }
+    }
+
+    /// Returns the number of pixels for each intensity (from `0` to `255`).
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Returns the number of pixels.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the mean intensity.
+    ///
+    /// Statistics of an empty histogram are `0`.
+    pub fn mean(&self) -> f32 {
+        self.stats().mean as f32
+    }
+
+    /// Returns the median intensity (i.e., the 50th percentile).
+    pub fn median(&self) -> u8 {
+        self.percentile(50.0)
+    }
+
+    /// Returns the standard deviation of the intensities.
+    pub fn std_dev(&self) -> f32 {
+        self.stats().std_dev as f32
+    }
+
+    /// Returns the smallest intensity such that at least `p` percent of the pixels are not brighter than it.
+    ///
+    /// `p` is clamped to the range from `0.0` to `100.0`.
+    pub fn percentile(&self, p: f32) -> u8 {
+        let p = f64::from(p.clamp(0.0, 100.0));
+        let rank = ((p / 100.0 * self.total as f64).ceil() as usize).max(1);
+        let mut cumulative = 0;
+        for (i, &count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= rank {
+                return i as u8;
+            }
+        }
+        0
+    }
+
+    /// Returns the Shannon entropy of the intensities in bits (from `0.0` to `8.0`).
+    pub fn entropy(&self) -> f32 {
+        self.stats().entropy as f32
+    }
+
+    /// Returns the fraction of the pixels darker than `threshold`.
+    pub fn dark_fraction(&self, threshold: u8) -> f32 {
+        let count = self.counts[..usize::from(threshold)].iter().sum();
+        self.fraction(count)
+    }
+
+    /// Returns the fraction of the pixels brighter than `threshold`.
+    pub fn bright_fraction(&self, threshold: u8) -> f32 {
+        let count = self.counts[usize::from(threshold) + 1..].iter().sum();
+        self.fraction(count)
+    }
+
+    /// Returns the number of pixels clipped to black (i.e., having the intensity `0`).
+    pub fn clipped_black(&self) -> usize {
+        self.counts[0]
+    }
+
+    /// Returns the number of pixels clipped to white (i.e., having the intensity `255`).
+    pub fn clipped_white(&self) -> usize {
+        self.counts[255]
+    }
+
+    fn stats(&self) -> Stats {
+        Stats::new(self.counts.iter().map(|&count| count as f64))
+    }
+
+    fn fraction(&self, count: usize) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (count as f64 / self.total as f64) as f32
+    }
+}
+
+/// Statistics of the bin indices of a histogram, shared by [`Histogram`] and the PDFs used to compute curves.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stats {
+    /// Mean bin index.
+    pub(crate) mean: f64,
+
+    /// Standard deviation of the bin indices.
+    pub(crate) std_dev: f64,
+
+    /// Shannon entropy in bits.
+    pub(crate) entropy: f64,
+}
+
+impl Stats {
+    /// Computes the statistics from the (not necessarily normalized) weights of the bins.
+    ///
+    /// All the statistics are `0` if the weights sum to `0`.
+    pub(crate) fn new<I>(weights: I) -> Self
+    where
+        I: Clone + Iterator<Item = f64>,
+    {
+        let total = weights.clone().sum::<f64>();
+        if total <= 0.0 {
+            return Self {
+                mean: 0.0,
+                std_dev: 0.0,
+                entropy: 0.0,
+            };
+        }
+        let (mut mean, mut entropy) = (0.0, 0.0);
+        for (i, w) in weights.clone().enumerate() {
+            let p = w / total;
+            mean += i as f64 * p;
+            if p > 0.0 {
+                entropy -= p * p.log2();
+            }
+        }
+        let variance = weights
+            .enumerate()
+            .map(|(i, w)| (i as f64 - mean).powi(2) * w / total)
+            .sum::<f64>();
+        Self {
+            mean,
+            std_dev: variance.sqrt(),
+            entropy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agcwd;
+
+    #[test]
+    fn statistics_work() {
+        let pixels = [0, 0, 10, 20, 30, 255, 255, 255];
+        let histogram = Histogram::new(&pixels, PixelFormat::Gray, IntensityModel::HsvValue);
+        assert_eq!(histogram.total(), 8);
+        assert_eq!(histogram.mean(), 103.125);
+        assert_eq!(histogram.median(), 20);
+        assert_eq!(histogram.percentile(0.0), 0);
+        assert_eq!(histogram.percentile(75.0), 255);
+        assert_eq!(histogram.percentile(100.0), 255);
+        assert!((histogram.std_dev() - 118.0).abs() < 0.01);
+        assert!((histogram.entropy() - 2.156).abs() < 0.001);
+        assert_eq!(histogram.dark_fraction(20), 0.375);
+        assert_eq!(histogram.bright_fraction(30), 0.375);
+        assert_eq!(histogram.clipped_black(), 2);
+        assert_eq!(histogram.clipped_white(), 3);
+
+        let empty = Histogram::new(&[], PixelFormat::Rgb, IntensityModel::HsvValue);
+        assert_eq!(
+            (empty.mean(), empty.median(), empty.entropy()),
+            (0.0, 0, 0.0)
+        );
+    }
+
+    #[test]
+    fn histogram_curve_is_consistent_with_curve() {
+        let pixels = [1, 2, 3, 40, 50, 60, 200, 100, 0];
+        let agcwd = Agcwd::new();
+        let histogram = agcwd.compute_histogram(&pixels, PixelFormat::Rgb);
+        assert_eq!(histogram.counts()[200], 1);
+        assert_eq!(
+            agcwd.compute_histogram_curve(&histogram),
+            agcwd.compute_rgb_curve(&pixels)
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{AgcwdError, Geometry, Image, ImageMut, PixelFormat};
+
+/// A view of an image stored in a (possibly padded) buffer.
+///
+/// The pixels of a row are contiguous, and the first bytes of successive rows are `stride` bytes apart.
+/// The bytes between the end of a row and the start of the next row (i.e., padding) are neither read nor modified.
+///
+/// `B` is usually `&[u8]`, `&mut [u8]` or `Vec<u8>`.
+///
+/// Views hold 8-bit samples only. 16-bit and floating-point images are passed as contiguous slices
+/// (e.g., [`Agcwd::enhance_image16_with_mask()`](crate::Agcwd::enhance_image16_with_mask)),
+/// as are 8-bit images without padding (e.g., [`Agcwd::enhance_image_with_mask()`](crate::Agcwd::enhance_image_with_mask)).
+#[derive(Debug, Clone)]
+pub struct ImageView<B> {
+    pixels: B,
+    format: PixelFormat,
+    geometry: Geometry,
+}
+
+impl<B: AsRef<[u8]>> ImageView<B> {
+    /// Makes a new [`ImageView`] instance.
+    ///
+    /// `stride` is the distance in bytes between the first bytes of two successive rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride` is smaller than a row of pixels or `pixels` is too short to hold the image.
+    pub fn new(pixels: B, format: PixelFormat, width: usize, height: usize, stride: usize) -> Self {
+        Self::try_new(pixels, format, width, height, stride).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Makes a new [`ImageView`] instance, returning an error instead of panicking.
+    ///
+    /// See [`ImageView::new()`] for the conditions.
+    pub fn try_new(
+        pixels: B,
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Result<Self, AgcwdError> {
+        let row_len = width * format.channels();
+        if stride < row_len {
+            return Err(AgcwdError::InvalidStride { stride, row_len });
+        }
+        if height > 0 {
+            let required = (height - 1) * stride + row_len;
+            let len = pixels.as_ref().len();
+            if len < required {
+                return Err(AgcwdError::BufferTooShort { len, required });
+            }
+        }
+        Ok(Self {
+            pixels,
+            format,
+            geometry: Geometry {
+                width,
+                height,
+                stride,
+            },
+        })
+    }
+
+    /// Returns the pixel format of this image.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Returns the width (in pixels) of this image.
+    pub fn width(&self) -> usize {
+        self.geometry.width
+    }
+
+    /// Returns the height (in pixels) of this image.
+    pub fn height(&self) -> usize {
+        self.geometry.height
+    }
+
+    /// Returns the distance in bytes between the first bytes of two successive rows.
+    pub fn stride(&self) -> usize {
+        self.geometry.stride
+    }
+
+    /// Returns the underlying buffer.
+    pub fn pixels(&self) -> &[u8] {
+        self.pixels.as_ref()
+    }
+
+    /// Returns the underlying buffer, consuming this view.
+    pub fn into_inner(self) -> B {
+        self.pixels
+    }
+
+    /// Returns a view of the sub-rectangle of this image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not contained in this image.
+    pub fn sub_view(&self, x: usize, y: usize, width: usize, height: usize) -> ImageView<&[u8]> {
+        let offset = self.sub_view_offset(x, y, width, height);
+        ImageView::new(
+            &self.pixels.as_ref()[offset..],
+            self.format,
+            width,
+            height,
+            self.geometry.stride,
+        )
+    }
+
+    fn sub_view_offset(&self, x: usize, y: usize, width: usize, height: usize) -> usize {
+        assert!(
+            x + width <= self.geometry.width && y + height <= self.geometry.height,
+            "out of bounds"
+        );
+        if width == 0 || height == 0 {
+            return 0;
+        }
+        y * self.geometry.stride + x * self.format.channels()
+    }
+
+    pub(crate) fn check_mask(&self, mask: &[u8]) {
+        crate::check_mask(mask, self.geometry.width * self.geometry.height);
+    }
+
+    pub(crate) fn as_image(&self) -> Image<'_> {
+        Image::with_geometry(self.pixels.as_ref(), self.format, self.geometry)
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> ImageView<B> {
+    /// Returns a mutable view of the sub-rectangle of this image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not contained in this image.
+    pub fn sub_view_mut(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> ImageView<&mut [u8]> {
+        let offset = self.sub_view_offset(x, y, width, height);
+        ImageView::new(
+            &mut self.pixels.as_mut()[offset..],
+            self.format,
+            width,
+            height,
+            self.geometry.stride,
+        )
+    }
+
+    pub(crate) fn as_image_mut(&mut self) -> ImageMut<'_> {
+        ImageMut::with_geometry(self.pixels.as_mut(), self.format, self.geometry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agcwd;
+
+    #[test]
+    fn padding_is_left_untouched() {
+        let rgb = [1, 2, 3, 40, 50, 60, 200, 100, 0, 9, 9, 9];
+        let agcwd = Agcwd::new();
+
+        let mut expected = rgb;
+        agcwd.enhance_rgb_image(&mut expected);
+
+        // 2x2 RGB image with 2 padding bytes per row.
+        let mut pixels = [0xFF; 16];
+        pixels[0..6].copy_from_slice(&rgb[0..6]);
+        pixels[8..14].copy_from_slice(&rgb[6..12]);
+        let mut image = ImageView::new(&mut pixels[..], PixelFormat::Rgb, 2, 2, 8);
+        agcwd.enhance_image_view(&mut image);
+
+        assert_eq!(pixels[0..6], expected[0..6]);
+        assert_eq!(pixels[8..14], expected[6..12]);
+        assert_eq!([pixels[6], pixels[7], pixels[14], pixels[15]], [0xFF; 4]);
+    }
+
+    #[test]
+    fn sub_view_works() {
+        // 3x2 grayscale image.
+        let mut pixels = vec![1, 2, 3, 4, 5, 6];
+        let mut image = ImageView::new(&mut pixels, PixelFormat::Gray, 3, 2, 3);
+        let mut sub = image.sub_view_mut(1, 0, 2, 2);
+        assert_eq!(
+            sub.as_image().intensities().collect::<Vec<_>>(),
+            [2, 3, 5, 6]
+        );
+
+        Agcwd::new().enhance_image_view(&mut sub);
+        assert_eq!([pixels[0], pixels[3]], [1, 4]);
+    }
+
+    #[test]
+    fn mask_works() {
+        let original = [10, 20, 30, 40, 200, 210, 220, 230];
+        let agcwd = Agcwd::new();
+
+        // Only the masked pixels are used to build the histogram.
+        let mut expected = [10, 20, 30, 40];
+        agcwd.enhance_gray_image(&mut expected);
+
+        let mask = [255, 255, 255, 255, 0, 0, 0, 0];
+        let mut pixels = original;
+        let mut image = ImageView::new(&mut pixels[..], PixelFormat::Gray, 4, 2, 4);
+        agcwd.enhance_image_view_with_mask(&mut image, &mask);
+        assert_eq!(pixels[..4], expected);
+        assert_eq!(pixels[4..], original[4..]);
+
+        // Soft masks blend the original and enhanced pixels.
+        let mask = [255, 255, 255, 128, 0, 0, 0, 0];
+        let mut pixels = original;
+        let mut image = ImageView::new(&mut pixels[..], PixelFormat::Gray, 4, 2, 4);
+        let curve = agcwd.compute_image_view_curve_with_mask(&image, &mask);
+        curve.apply_image_view_with_mask(&mut image, &mask);
+        let enhanced = u32::from(curve.get(40));
+        assert_eq!(pixels[2], curve.get(30));
+        assert_eq!(
+            u32::from(pixels[3]),
+            (40 * 127 + enhanced * 128 + 127) / 255
+        );
+        assert_eq!(pixels[4..], original[4..]);
+    }
+
+    #[test]
+    fn all_zero_mask_leaves_image_unchanged() {
+        let original = [10, 20, 30, 40, 200, 210, 220, 230];
+        let mask = [0; 8];
+        let mut pixels = original;
+        let mut image = ImageView::new(&mut pixels[..], PixelFormat::Gray, 4, 2, 4);
+        let curve = Agcwd::new().compute_image_view_curve_with_mask(&image, &mask);
+        assert!(curve
+            .as_array()
+            .iter()
+            .enumerate()
+            .all(|(i, &v)| usize::from(v) == i));
+
+        // The identity curve also leaves the pixels unchanged without the mask.
+        curve.apply_image_view(&mut image);
+        assert_eq!(pixels, original);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::color_format;
+use crate::Curve;
+
+/// Color model used to derive the intensity of an RGB pixel.
+///
+/// Grayscale pixels always use the gray level itself as the intensity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntensityModel {
+    /// V component of the HSV color model (i.e., `max(R, G, B)`).
+    #[default]
+    HsvValue,
+
+    /// Luma (Y') defined by ITU-R BT.601.
+    ///
+    /// The chroma components (Cb and Cr) are kept when the luma is enhanced.
+    Bt601Luma,
+
+    /// Luma (Y') defined by ITU-R BT.709.
+    ///
+    /// The chroma components (Cb and Cr) are kept when the luma is enhanced.
+    Bt709Luma,
+
+    /// L component of the HSL color model (i.e., `(max(R, G, B) + min(R, G, B)) / 2`).
+    HslLightness,
+
+    /// L* component of the CIELAB color space (the pixels are regarded as sRGB with the D65 white point).
+    ///
+    /// L* is scaled from `0..=100` to `0..=255` to be used as an intensity.
+    CielabLightness,
+}
+
+impl IntensityModel {
+    pub(crate) fn intensity(self, r: u8, g: u8, b: u8) -> u8 {
+        match self {
+            Self::HsvValue => std::cmp::max(r, std::cmp::max(g, b)),
+            Self::Bt601Luma => {
+                let (y, _, _) = color_format::rgb_to_ycbcr(r, g, b, color_format::BT601);
+                y.round() as u8
+            }
+            Self::Bt709Luma => {
+                let (y, _, _) = color_format::rgb_to_ycbcr(r, g, b, color_format::BT709);
+                y.round() as u8
+            }
+            Self::HslLightness => {
+                let max = std::cmp::max(r, std::cmp::max(g, b));
+                let min = std::cmp::min(r, std::cmp::min(g, b));
+                (u16::from(max) + u16::from(min)).div_ceil(2) as u8
+            }
+            Self::CielabLightness => {
+                let (l, _, _) = color_format::rgb_to_lab(r, g, b);
+                (l * 2.55).round() as u8
+            }
+        }
+    }
+
+    pub(crate) fn apply(self, r: u8, g: u8, b: u8, curve: &Curve) -> (u8, u8, u8) {
+        match self {
+            Self::HsvValue => {
+                let (h, s, v) = color_format::rgb_to_hsv(r, g, b);
+                color_format::hsv_to_rgb(h, s, curve.get(v))
+            }
+            Self::Bt601Luma | Self::Bt709Luma => {
+                let k = if self == Self::Bt601Luma {
+                    color_format::BT601
+                } else {
+                    color_format::BT709
+                };
+                let (y, cb, cr) = color_format::rgb_to_ycbcr(r, g, b, k);
+                color_format::ycbcr_to_rgb(curve.interpolate(y), cb, cr, k)
+            }
+            Self::HslLightness => {
+                let (h, s, l) = color_format::rgb_to_hsl(r, g, b);
+                color_format::hsl_to_rgb(h, s, curve.interpolate(l))
+            }
+            Self::CielabLightness => {
+                let (l, a, b) = color_format::rgb_to_lab(r, g, b);
+                color_format::lab_to_rgb(curve.interpolate(l * 2.55) / 2.55, a, b)
+            }
+        }
+    }
+}
@@ -13,15 +13,60 @@
 //! let agcwd = agcwd::Agcwd::new();
 //! agcwd.enhance_rgb_image(&mut pixels);
 //! ```
+//!
+//! # Features
+//!
+//! - `cli`: Builds the `agcwd` command to enhance PNG images (this does not affect the library).
+//! - `image`: Adds methods to enhance images of the [image](https://crates.io/crates/image) crate
+#![cfg_attr(
+    feature = "image",
+    doc = "  (e.g., [`Agcwd::enhance_dynamic_image()`])."
+)]
+#![cfg_attr(
+    not(feature = "image"),
+    doc = "  (e.g., `Agcwd::enhance_dynamic_image()`)."
+)]
+//!   Note that typed buffers such as `image::RgbImage` can be passed to the methods taking slices
+//!   (e.g., [`Agcwd::enhance_rgb_image()`]) as they dereference to slices.
+//! - `rayon`: Builds histograms and applies curves in parallel using [rayon](https://crates.io/crates/rayon).
+//!   The results are identical to those of the sequential implementation.
 #![warn(missing_docs)]
 
+pub use self::error::AgcwdError;
+pub use self::histogram::Histogram;
+pub use self::image_view::ImageView;
+pub use self::intensity_model::IntensityModel;
+pub use self::local::{AgcwdLocal, AgcwdLocalOptions};
+pub use self::pixel_format::PixelFormat;
+pub use self::scene_change::SceneChangeDetector;
+pub use self::variant::AgcwdVariant;
+pub use self::video::{AgcwdVideo, AgcwdVideoOptions};
+pub use self::yuv_format::YuvFormat;
+
 mod color_format;
+#[cfg(feature = "image")]
+mod dynamic_image;
+mod error;
+mod histogram;
+mod image_view;
+mod intensity_model;
+mod local;
+pub mod metrics;
+mod pixel_format;
+mod scene_change;
+mod simd;
+mod variant;
+mod video;
+mod yuv_format;
 
 /// [`Agcwd`] options.
 #[derive(Debug, Clone)]
 pub struct AgcwdOptions {
     /// An algorithm parameter to adjust the shape of weighting distribution (WD).
     ///
+    /// Must be a finite non-negative value.
+    /// Ignored by [`AgcwdVariant::Improved`] and if `auto_alpha` is `true`.
+    ///
     /// Defaults to `0.5`.
     pub alpha: f32,
 
@@ -29,127 +74,1703 @@ pub struct AgcwdOptions {
     ///
     /// Note that this is a this crate specific parameter (not defined by the AGCWD paper).
     ///
+    /// Must be in the range from `0.0` to `1.0`.
+    ///
     /// Defaults to `0.0` (i.e., fusion is disabled).
     pub fusion: f32,
+
+    /// If `true`, `alpha` is selected for each image from the statistics of its histogram
+    /// (the value of the `alpha` option is ignored).
+    ///
+    /// Images having a narrow histogram (i.e., a small standard deviation and a low entropy) get a large `alpha`
+    /// (up to `0.75`) to be stretched strongly, and images having a widely spread histogram get a small `alpha`
+    /// (down to `0.25`) to be enhanced gently.
+    ///
+    /// The selected value can be obtained by [`Curve::alpha()`] (or [`Curve16::alpha()`] and [`CurveF32::alpha()`]).
+    ///
+    /// Defaults to `false`.
+    pub auto_alpha: bool,
+
+    /// If `true`, `fusion` is selected for each image from its mean intensity
+    /// (the value of the `fusion` option is ignored).
+    ///
+    /// Images darker than the middle gray are not fused, and brighter images are fused more as they get brighter
+    /// to prevent over-enhancement.
+    ///
+    /// The selected value can be obtained by [`Curve::fusion()`] (or [`Curve16::fusion()`] and [`CurveF32::fusion()`]).
+    ///
+    /// Defaults to `false`.
+    pub auto_fusion: bool,
+
+    /// Number of histogram bins used to enhance 16-bit and floating-point images.
+    ///
+    /// The value is clamped to the range from `2` to `65536` (a single bin cannot tell dark pixels from bright ones),
+    /// and the fallible (`try_`) methods reject values out of the range.
+    /// 8-bit images always use 256 bins.
+    ///
+    /// Defaults to `4096`.
+    pub histogram_bins: usize,
+
+    /// Color model used to derive the intensity of an 8-bit RGB pixel.
+    ///
+    /// 16-bit and floating-point images always use [`IntensityModel::HsvValue`].
+    ///
+    /// Defaults to [`IntensityModel::HsvValue`].
+    pub intensity_model: IntensityModel,
+
+    /// If `true`, the R, G and B components of an 8-bit pixel are multiplied by `curve[i] / i`
+    /// (where `i` is the intensity of the pixel) instead of being converted to and from the color model.
+    ///
+    /// This preserves the ratios between the components (i.e., hue and saturation) exactly,
+    /// and an identity curve leaves the pixels unchanged.
+    /// Components exceeding `255` after the scaling (only possible with intensity models other than
+    /// [`IntensityModel::HsvValue`]) are clipped.
+    /// This is also faster than the default, and vectorized with [`IntensityModel::HsvValue`].
+    ///
+    /// With [`IntensityModel::HsvValue`], the results differ from those of the default (which quantizes
+    /// hue and saturation to 8 bits) by a few levels per component (at most `7` for typical curves,
+    /// and less than `1` on average).
+    ///
+    /// Defaults to `false`.
+    pub exact_hue: bool,
+
+    /// Variant of the algorithm.
+    ///
+    /// Use [`AgcwdVariant::Improved`] to prevent bright images from being over-enhanced.
+    ///
+    /// Defaults to [`AgcwdVariant::Original`].
+    pub variant: AgcwdVariant,
 }
 
 impl Default for AgcwdOptions {
     fn default() -> Self {
         Self {
-            alpha: 0.5,
-            fusion: 0.0,
+            alpha: 0.5,
+            fusion: 0.0,
+            auto_alpha: false,
+            auto_fusion: false,
+            histogram_bins: 4096,
+            intensity_model: IntensityModel::HsvValue,
+            exact_hue: false,
+            variant: AgcwdVariant::Original,
+        }
+    }
+}
+
+impl AgcwdOptions {
+    /// Checks whether the options have valid values.
+    pub fn validate(&self) -> Result<(), AgcwdError> {
+        if !(self.alpha.is_finite() && self.alpha >= 0.0) {
+            return Err(AgcwdError::InvalidOption {
+                name: "alpha",
+                value: self.alpha,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.fusion) {
+            return Err(AgcwdError::InvalidOption {
+                name: "fusion",
+                value: self.fusion,
+            });
+        }
+        if !(2..=65536).contains(&self.histogram_bins) {
+            return Err(AgcwdError::InvalidOption {
+                name: "histogram_bins",
+                value: self.histogram_bins as f32,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// [`Agcwd`] provides methods to enhance image contrast based on the [AGCWD] algorithm.
+///
+/// [AGCWD]: https://ieeexplore.ieee.org/abstract/document/6336819/
+#[derive(Debug, Default)]
+pub struct Agcwd {
+    options: AgcwdOptions,
+}
+
+impl Agcwd {
+    /// Makes a new [`Agcwd`] instance with the default options.
+    pub fn new() -> Self {
+        Self::with_options(Default::default())
+    }
+
+    /// Makes a new [`Agcwd`] instance with the given options.
+    pub fn with_options(options: AgcwdOptions) -> Self {
+        Self { options }
+    }
+
+    /// Makes a new [`Agcwd`] instance with the given options, returning an error if they are invalid.
+    pub fn try_with_options(options: AgcwdOptions) -> Result<Self, AgcwdError> {
+        options.validate()?;
+        Ok(Self { options })
+    }
+
+    /// Enhances the contrast of a grayscale image.
+    pub fn enhance_gray_image(&self, pixels: &mut [u8]) {
+        self.enhance_image(pixels, PixelFormat::Gray);
+    }
+
+    /// Enhances the contrast of a grayscale image with an alpha channel.
+    pub fn enhance_gray_alpha_image(&self, pixels: &mut [u8]) {
+        self.enhance_image(pixels, PixelFormat::GrayAlpha);
+    }
+
+    /// Enhances the contrast of an RGB image.
+    pub fn enhance_rgb_image(&self, pixels: &mut [u8]) {
+        self.enhance_image(pixels, PixelFormat::Rgb);
+    }
+
+    /// Enhances the contrast of an RGBA image.
+    pub fn enhance_rgba_image(&self, pixels: &mut [u8]) {
+        self.enhance_image(pixels, PixelFormat::Rgba);
+    }
+
+    /// Enhances the contrast of a grayscale image, writing the result to `dst` instead of modifying `src`.
+    ///
+    /// See [`Agcwd::enhance_image_into()`] for the details.
+    pub fn enhance_gray_image_into(&self, src: &[u8], dst: &mut [u8]) {
+        self.enhance_image_into(src, PixelFormat::Gray, dst, PixelFormat::Gray);
+    }
+
+    /// Enhances the contrast of a grayscale image with an alpha channel, writing the result to `dst` instead of modifying `src`.
+    ///
+    /// See [`Agcwd::enhance_image_into()`] for the details.
+    pub fn enhance_gray_alpha_image_into(&self, src: &[u8], dst: &mut [u8]) {
+        self.enhance_image_into(src, PixelFormat::GrayAlpha, dst, PixelFormat::GrayAlpha);
+    }
+
+    /// Enhances the contrast of an RGB image, writing the result to `dst` instead of modifying `src`.
+    ///
+    /// See [`Agcwd::enhance_image_into()`] for the details.
+    pub fn enhance_rgb_image_into(&self, src: &[u8], dst: &mut [u8]) {
+        self.enhance_image_into(src, PixelFormat::Rgb, dst, PixelFormat::Rgb);
+    }
+
+    /// Enhances the contrast of an RGBA image, writing the result to `dst` instead of modifying `src`.
+    ///
+    /// See [`Agcwd::enhance_image_into()`] for the details.
+    pub fn enhance_rgba_image_into(&self, src: &[u8], dst: &mut [u8]) {
+        self.enhance_image_into(src, PixelFormat::Rgba, dst, PixelFormat::Rgba);
+    }
+
+    /// Enhances the contrast of a 16-bit grayscale image.
+    pub fn enhance_gray16_image(&self, pixels: &mut [u16]) {
+        self.enhance_image16(pixels, PixelFormat::Gray);
+    }
+
+    /// Enhances the contrast of a 16-bit grayscale image with an alpha channel.
+    pub fn enhance_gray_alpha16_image(&self, pixels: &mut [u16]) {
+        self.enhance_image16(pixels, PixelFormat::GrayAlpha);
+    }
+
+    /// Enhances the contrast of a 16-bit RGB image.
+    pub fn enhance_rgb16_image(&self, pixels: &mut [u16]) {
+        self.enhance_image16(pixels, PixelFormat::Rgb);
+    }
+
+    /// Enhances the contrast of a 16-bit RGBA image.
+    pub fn enhance_rgba16_image(&self, pixels: &mut [u16]) {
+        self.enhance_image16(pixels, PixelFormat::Rgba);
+    }
+
+    /// Enhances the contrast of a floating-point grayscale image.
+    ///
+    /// See [`CurveF32`] for the expected range of the pixel values.
+    pub fn enhance_gray_f32_image(&self, pixels: &mut [f32]) {
+        self.enhance_image_f32(pixels, PixelFormat::Gray);
+    }
+
+    /// Enhances the contrast of a floating-point grayscale image with an alpha channel.
+    ///
+    /// See [`CurveF32`] for the expected range of the pixel values.
+    pub fn enhance_gray_alpha_f32_image(&self, pixels: &mut [f32]) {
+        self.enhance_image_f32(pixels, PixelFormat::GrayAlpha);
+    }
+
+    /// Enhances the contrast of a floating-point RGB image.
+    ///
+    /// See [`CurveF32`] for the expected range of the pixel values.
+    pub fn enhance_rgb_f32_image(&self, pixels: &mut [f32]) {
+        self.enhance_image_f32(pixels, PixelFormat::Rgb);
+    }
+
+    /// Enhances the contrast of a floating-point RGBA image.
+    ///
+    /// See [`CurveF32`] for the expected range of the pixel values.
+    pub fn enhance_rgba_f32_image(&self, pixels: &mut [f32]) {
+        self.enhance_image_f32(pixels, PixelFormat::Rgba);
+    }
+
+    /// Computes the intensity transformation curve of a grayscale image without modifying it.
+    ///
+    /// The resulting [`Curve`] can be applied to the same image or to other images later.
+    pub fn compute_gray_curve(&self, pixels: &[u8]) -> Curve {
+        self.compute_curve(pixels, PixelFormat::Gray)
+    }
+
+    /// Computes the intensity transformation curve of a grayscale image with an alpha channel without modifying it.
+    ///
+    /// The resulting [`Curve`] can be applied to the same image or to other images later.
+    pub fn compute_gray_alpha_curve(&self, pixels: &[u8]) -> Curve {
+        self.compute_curve(pixels, PixelFormat::GrayAlpha)
+    }
+
+    /// Computes the intensity transformation curve of an RGB image without modifying it.
+    ///
+    /// The resulting [`Curve`] can be applied to the same image or to other images later.
+    pub fn compute_rgb_curve(&self, pixels: &[u8]) -> Curve {
+        self.compute_curve(pixels, PixelFormat::Rgb)
+    }
+
+    /// Computes the intensity transformation curve of an RGBA image without modifying it.
+    ///
+    /// The resulting [`Curve`] can be applied to the same image or to other images later.
+    pub fn compute_rgba_curve(&self, pixels: &[u8]) -> Curve {
+        self.compute_curve(pixels, PixelFormat::Rgba)
+    }
+
+    /// Computes the intensity transformation curve of a 16-bit grayscale image without modifying it.
+    pub fn compute_gray16_curve(&self, pixels: &[u16]) -> Curve16 {
+        self.compute_curve16(pixels, PixelFormat::Gray)
+    }
+
+    /// Computes the intensity transformation curve of a 16-bit grayscale image with an alpha channel without modifying it.
+    pub fn compute_gray_alpha16_curve(&self, pixels: &[u16]) -> Curve16 {
+        self.compute_curve16(pixels, PixelFormat::GrayAlpha)
+    }
+
+    /// Computes the intensity transformation curve of a 16-bit RGB image without modifying it.
+    pub fn compute_rgb16_curve(&self, pixels: &[u16]) -> Curve16 {
+        self.compute_curve16(pixels, PixelFormat::Rgb)
+    }
+
+    /// Computes the intensity transformation curve of a 16-bit RGBA image without modifying it.
+    pub fn compute_rgba16_curve(&self, pixels: &[u16]) -> Curve16 {
+        self.compute_curve16(pixels, PixelFormat::Rgba)
+    }
+
+    /// Computes the intensity transformation curve of a floating-point grayscale image without modifying it.
+    pub fn compute_gray_f32_curve(&self, pixels: &[f32]) -> CurveF32 {
+        self.compute_curve_f32(pixels, PixelFormat::Gray)
+    }
+
+    /// Computes the intensity transformation curve of a floating-point grayscale image with an alpha channel without modifying it.
+    pub fn compute_gray_alpha_f32_curve(&self, pixels: &[f32]) -> CurveF32 {
+        self.compute_curve_f32(pixels, PixelFormat::GrayAlpha)
+    }
+
+    /// Computes the intensity transformation curve of a floating-point RGB image without modifying it.
+    pub fn compute_rgb_f32_curve(&self, pixels: &[f32]) -> CurveF32 {
+        self.compute_curve_f32(pixels, PixelFormat::Rgb)
+    }
+
+    /// Computes the intensity transformation curve of a floating-point RGBA image without modifying it.
+    pub fn compute_rgba_f32_curve(&self, pixels: &[f32]) -> CurveF32 {
+        self.compute_curve_f32(pixels, PixelFormat::Rgba)
+    }
+
+    /// Enhances the contrast of an image having the given pixel format.
+    pub fn enhance_image(&self, pixels: &mut [u8], format: PixelFormat) {
+        let curve = self.compute_curve(pixels, format);
+        curve.apply_image(pixels, format);
+    }
+
+    /// Enhances the contrast of the pixels of an image selected by a mask.
+    ///
+    /// See [`Agcwd::enhance_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn enhance_image_with_mask(&self, pixels: &mut [u8], format: PixelFormat, mask: &[u8]) {
+        let curve = self.compute_curve_with_mask(pixels, format, mask);
+        curve.apply_image_with_mask(pixels, format, mask);
+    }
+
+    /// Enhances the contrast of an image having the pixel format `src_format`,
+    /// writing the result to `dst` having the pixel format `dst_format` instead of modifying `src`.
+    ///
+    /// The curve is computed from `src`. See [`Curve::apply_image_into()`] for the conversion between the formats.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` and `dst` hold different numbers of pixels.
+    pub fn enhance_image_into(
+        &self,
+        src: &[u8],
+        src_format: PixelFormat,
+        dst: &mut [u8],
+        dst_format: PixelFormat,
+    ) {
+        let curve = self.compute_curve(src, src_format);
+        curve.apply_image_into(src, src_format, dst, dst_format);
+    }
+
+    /// Enhances the contrast of an image view.
+    pub fn enhance_image_view<B>(&self, image: &mut ImageView<B>)
+    where
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let curve = self.compute_image_view_curve(image);
+        curve.apply_image_view(image);
+    }
+
+    /// Enhances the contrast of the pixels of an image view selected by a mask.
+    ///
+    /// `mask` holds a weight (from `0` to `255`) for each pixel in row-major order (without padding).
+    /// The weights are used both to build the histogram (pixels with a weight of `0` are ignored)
+    /// and to blend the original pixels and the enhanced ones (see [`Curve::apply_image_view_with_mask()`]).
+    ///
+    /// To enhance a rectangular region of interest, use [`ImageView::sub_view_mut()`] instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn enhance_image_view_with_mask<B>(&self, image: &mut ImageView<B>, mask: &[u8])
+    where
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let curve = self.compute_image_view_curve_with_mask(image, mask);
+        curve.apply_image_view_with_mask(image, mask);
+    }
+
+    /// Enhances the contrast of a YUV image by applying the curve to its luminance samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` is too short to hold a `width` x `height` image.
+    pub fn enhance_yuv_image(
+        &self,
+        pixels: &mut [u8],
+        format: YuvFormat,
+        width: usize,
+        height: usize,
+    ) {
+        self.enhance_image_view(&mut format.luma_view(pixels, width, height));
+    }
+
+    /// Enhances the contrast of a 16-bit image having the given pixel format.
+    pub fn enhance_image16(&self, pixels: &mut [u16], format: PixelFormat) {
+        let curve = self.compute_curve16(pixels, format);
+        curve.apply_image(pixels, format);
+    }
+
+    /// Enhances the contrast of the pixels of a 16-bit image selected by a mask.
+    ///
+    /// See [`Agcwd::enhance_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn enhance_image16_with_mask(&self, pixels: &mut [u16], format: PixelFormat, mask: &[u8]) {
+        let curve = self.compute_curve16_with_mask(pixels, format, mask);
+        curve.apply_image_with_mask(pixels, format, mask);
+    }
+
+    /// Enhances the contrast of a floating-point image having the given pixel format.
+    ///
+    /// See [`CurveF32`] for the expected range of the pixel values.
+    pub fn enhance_image_f32(&self, pixels: &mut [f32], format: PixelFormat) {
+        let curve = self.compute_curve_f32(pixels, format);
+        curve.apply_image(pixels, format);
+    }
+
+    /// Enhances the contrast of the pixels of a floating-point image selected by a mask.
+    ///
+    /// See [`Agcwd::enhance_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn enhance_image_f32_with_mask(
+        &self,
+        pixels: &mut [f32],
+        format: PixelFormat,
+        mask: &[u8],
+    ) {
+        let curve = self.compute_curve_f32_with_mask(pixels, format, mask);
+        curve.apply_image_with_mask(pixels, format, mask);
+    }
+
+    /// Computes the intensity transformation curve of an image having the given pixel format without modifying it.
+    pub fn compute_curve(&self, pixels: &[u8], format: PixelFormat) -> Curve {
+        let pdf = Pdf::new(&Image::new(pixels, format), self.options.intensity_model);
+        self.curve_from_pdf(&pdf)
+    }
+
+    /// Computes the intensity transformation curve of the pixels of an image weighted by a mask.
+    ///
+    /// See [`Agcwd::enhance_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn compute_curve_with_mask(
+        &self,
+        pixels: &[u8],
+        format: PixelFormat,
+        mask: &[u8],
+    ) -> Curve {
+        check_mask(mask, pixels.len() / format.channels());
+        let pdf = Pdf::new(
+            &Image::new(pixels, format).with_mask(Some(mask)),
+            self.options.intensity_model,
+        );
+        self.curve_from_pdf(&pdf)
+    }
+
+    /// Computes the intensity transformation curve of a YUV image from its luminance samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` is too short to hold a `width` x `height` image.
+    pub fn compute_yuv_curve(
+        &self,
+        pixels: &[u8],
+        format: YuvFormat,
+        width: usize,
+        height: usize,
+    ) -> Curve {
+        self.compute_image_view_curve(&format.luma_view(pixels, width, height))
+    }
+
+    /// Computes the intensity transformation curve of an image view without modifying it.
+    pub fn compute_image_view_curve<B: AsRef<[u8]>>(&self, image: &ImageView<B>) -> Curve {
+        let pdf = Pdf::new(&image.as_image(), self.options.intensity_model);
+        self.curve_from_pdf(&pdf)
+    }
+
+    /// Computes the intensity transformation curve of the pixels of an image view weighted by a mask.
+    ///
+    /// See [`Agcwd::enhance_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn compute_image_view_curve_with_mask<B: AsRef<[u8]>>(
+        &self,
+        image: &ImageView<B>,
+        mask: &[u8],
+    ) -> Curve {
+        image.check_mask(mask);
+        let pdf = Pdf::new(
+            &image.as_image().with_mask(Some(mask)),
+            self.options.intensity_model,
+        );
+        self.curve_from_pdf(&pdf)
+    }
+
+    /// Computes the intensity histogram of an image having the given pixel format.
+    ///
+    /// The intensities are derived by [`AgcwdOptions::intensity_model`] in the same way as when computing a curve.
+    pub fn compute_histogram(&self, pixels: &[u8], format: PixelFormat) -> Histogram {
+        Histogram::new(pixels, format, self.options.intensity_model)
+    }
+
+    /// Computes the intensity transformation curve from a histogram computed beforehand.
+    ///
+    /// This is useful to avoid scanning an image twice when its statistics are also needed.
+    pub fn compute_histogram_curve(&self, histogram: &Histogram) -> Curve {
+        let pdf = Pdf::from_histogram(histogram.counts().to_vec());
+        self.curve_from_pdf(&pdf)
+    }
+
+    /// Computes the intensity transformation curve of a 16-bit image having the given pixel format without modifying it.
+    pub fn compute_curve16(&self, pixels: &[u16], format: PixelFormat) -> Curve16 {
+        self.curve16_from_image(&Image::new(pixels, format))
+    }
+
+    /// Computes the intensity transformation curve of the pixels of a 16-bit image weighted by a mask.
+    ///
+    /// See [`Agcwd::enhance_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn compute_curve16_with_mask(
+        &self,
+        pixels: &[u16],
+        format: PixelFormat,
+        mask: &[u8],
+    ) -> Curve16 {
+        check_mask(mask, pixels.len() / format.channels());
+        self.curve16_from_image(&Image::new(pixels, format).with_mask(Some(mask)))
+    }
+
+    fn curve16_from_image(&self, image: &Image<'_, u16>) -> Curve16 {
+        let bins = self.options.histogram_bins.clamp(2, 65536);
+        self.curve16_from_pdf(&Pdf::new16(image, bins))
+    }
+
+    fn curve16_from_pdf(&self, pdf: &Pdf) -> Curve16 {
+        let mut options = self.resolve_options(pdf);
+        let (cdf_w, negative) = Self::weighted_cdf(pdf, &mut options);
+        let mut curve = Curve16::new(&cdf_w, &options);
+        if negative {
+            curve.invert();
+        }
+        curve
+    }
+
+    /// Computes the intensity transformation curve of a floating-point image having the given pixel format without modifying it.
+    pub fn compute_curve_f32(&self, pixels: &[f32], format: PixelFormat) -> CurveF32 {
+        self.curve_f32_from_image(&Image::new(pixels, format))
+    }
+
+    /// Computes the intensity transformation curve of the pixels of a floating-point image weighted by a mask.
+    ///
+    /// See [`Agcwd::enhance_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn compute_curve_f32_with_mask(
+        &self,
+        pixels: &[f32],
+        format: PixelFormat,
+        mask: &[u8],
+    ) -> CurveF32 {
+        check_mask(mask, pixels.len() / format.channels());
+        self.curve_f32_from_image(&Image::new(pixels, format).with_mask(Some(mask)))
+    }
+
+    fn curve_f32_from_image(&self, image: &Image<'_, f32>) -> CurveF32 {
+        let bins = self.options.histogram_bins.clamp(2, 65536);
+        // Pixels ignored by the mask do not affect the range of the histogram.
+        let scale = image
+            .intensities()
+            .zip(image.weights())
+            .filter(|&(_, w)| w != 0)
+            .fold(1.0, |scale: f32, (v, _)| scale.max(v));
+        self.curve_f32_from_pdf(&Pdf::new_f32(image, bins, scale), scale)
+    }
+
+    fn curve_f32_from_pdf(&self, pdf: &Pdf, scale: f32) -> CurveF32 {
+        let mut options = self.resolve_options(pdf);
+        let (cdf_w, negative) = Self::weighted_cdf(pdf, &mut options);
+        CurveF32 {
+            cdf: cdf_w,
+            alpha: options.alpha,
+            fusion: options.fusion,
+            scale,
+            negative,
+        }
+    }
+
+    /// Enhances the contrast of an image having the given pixel format, returning an error if the input is invalid.
+    ///
+    /// Unlike [`Agcwd::enhance_image()`], this fails if the length of `pixels` is not a multiple of
+    /// the number of channels, `pixels` is empty, or the options are invalid.
+    /// `pixels` is left unchanged on failure.
+    pub fn try_enhance_image(
+        &self,
+        pixels: &mut [u8],
+        format: PixelFormat,
+    ) -> Result<(), AgcwdError> {
+        let curve = self.try_compute_curve(pixels, format)?;
+        curve.apply_image(pixels, format);
+        Ok(())
+    }
+
+    /// Enhances the contrast of a 16-bit image having the given pixel format, returning an error if the input is invalid.
+    ///
+    /// See [`Agcwd::try_enhance_image()`] for the possible errors.
+    pub fn try_enhance_image16(
+        &self,
+        pixels: &mut [u16],
+        format: PixelFormat,
+    ) -> Result<(), AgcwdError> {
+        let curve = self.try_compute_curve16(pixels, format)?;
+        curve.apply_image(pixels, format);
+        Ok(())
+    }
+
+    /// Enhances the contrast of a floating-point image having the given pixel format, returning an error if the input is invalid.
+    ///
+    /// See [`Agcwd::try_enhance_image()`] for the possible errors.
+    pub fn try_enhance_image_f32(
+        &self,
+        pixels: &mut [f32],
+        format: PixelFormat,
+    ) -> Result<(), AgcwdError> {
+        let curve = self.try_compute_curve_f32(pixels, format)?;
+        curve.apply_image(pixels, format);
+        Ok(())
+    }
+
+    /// Computes the intensity transformation curve of an image having the given pixel format, returning an error if the input is invalid.
+    ///
+    /// See [`Agcwd::try_enhance_image()`] for the possible errors.
+    pub fn try_compute_curve(
+        &self,
+        pixels: &[u8],
+        format: PixelFormat,
+    ) -> Result<Curve, AgcwdError> {
+        self.check_input(pixels, format)?;
+        Ok(self.compute_curve(pixels, format))
+    }
+
+    /// Computes the intensity transformation curve of a 16-bit image having the given pixel format, returning an error if the input is invalid.
+    ///
+    /// See [`Agcwd::try_enhance_image()`] for the possible errors.
+    pub fn try_compute_curve16(
+        &self,
+        pixels: &[u16],
+        format: PixelFormat,
+    ) -> Result<Curve16, AgcwdError> {
+        self.check_input(pixels, format)?;
+        Ok(self.compute_curve16(pixels, format))
+    }
+
+    /// Computes the intensity transformation curve of a floating-point image having the given pixel format, returning an error if the input is invalid.
+    ///
+    /// See [`Agcwd::try_enhance_image()`] for the possible errors.
+    pub fn try_compute_curve_f32(
+        &self,
+        pixels: &[f32],
+        format: PixelFormat,
+    ) -> Result<CurveF32, AgcwdError> {
+        self.check_input(pixels, format)?;
+        Ok(self.compute_curve_f32(pixels, format))
+    }
+
+    /// Enhances the contrast of an image view, returning an error if the input is invalid.
+    ///
+    /// This fails if the image has no pixels or the options are invalid.
+    /// Use [`ImageView::try_new()`] to check the size of the buffer of the image.
+    pub fn try_enhance_image_view<B>(&self, image: &mut ImageView<B>) -> Result<(), AgcwdError>
+    where
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        self.options.validate()?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(AgcwdError::EmptyImage);
+        }
+        self.enhance_image_view(image);
+        Ok(())
+    }
+
+    /// Enhances the contrast of a YUV image, returning an error if the input is invalid.
+    ///
+    /// Unlike [`Agcwd::enhance_yuv_image()`], this fails if `pixels` is too short to hold
+    /// a `width` x `height` image, the image has no pixels, or the options are invalid.
+    pub fn try_enhance_yuv_image(
+        &self,
+        pixels: &mut [u8],
+        format: YuvFormat,
+        width: usize,
+        height: usize,
+    ) -> Result<(), AgcwdError> {
+        self.try_enhance_image_view(&mut format.try_luma_view(pixels, width, height)?)
+    }
+
+    fn check_input<T>(&self, pixels: &[T], format: PixelFormat) -> Result<(), AgcwdError> {
+        self.options.validate()?;
+        if !pixels.len().is_multiple_of(format.channels()) {
+            return Err(AgcwdError::InvalidLength {
+                len: pixels.len(),
+                format,
+            });
+        }
+        if pixels.is_empty() {
+            return Err(AgcwdError::EmptyImage);
+        }
+        Ok(())
+    }
+
+    fn curve_from_pdf(&self, pdf: &Pdf) -> Curve {
+        let mut options = self.resolve_options(pdf);
+        let (cdf_w, negative) = Self::weighted_cdf(pdf, &mut options);
+        let mut curve = Curve::new(&cdf_w, &options);
+        if negative {
+            curve.invert();
+        }
+        curve
+    }
+
+    /// Returns the options with `alpha` and `fusion` selected from the PDF if they are automatic.
+    fn resolve_options(&self, pdf: &Pdf) -> AgcwdOptions {
+        let mut options = self.options.clone();
+        if options.auto_alpha {
+            // Both are `1.0` for a uniform histogram (`0.5 / sqrt(3)` is the standard deviation of it).
+            let spread = (pdf.std_dev() * 3f32.sqrt() * 2.0).min(1.0);
+            let entropy = pdf.entropy() / (pdf.0.len() as f32).log2().max(1.0);
+            options.alpha = (0.75 - 0.25 * spread - 0.25 * entropy).clamp(0.25, 0.75);
+        }
+        if options.auto_fusion {
+            options.fusion = ((pdf.mean() - 0.5) * 2.0).clamp(0.0, 1.0);
+        }
+        options
+    }
+
+    /// Returns the weighted CDF used to compute a curve,
+    /// and whether the curve should be applied to the negative image.
+    ///
+    /// `options.alpha` is updated to the value actually used.
+    fn weighted_cdf(pdf: &Pdf, options: &mut AgcwdOptions) -> (Cdf, bool) {
+        if options.variant == AgcwdVariant::Original {
+            let pdf_w = pdf.to_weighting_distribution(options.alpha);
+            return (Cdf::new(&pdf_w), false);
+        }
+
+        let t = (pdf.mean() - AgcwdVariant::IMPROVED_MEAN) / AgcwdVariant::IMPROVED_MEAN;
+        if t < -AgcwdVariant::IMPROVED_THRESHOLD {
+            options.alpha = AgcwdVariant::IMPROVED_DIMMED_ALPHA;
+            let pdf_w = pdf.to_weighting_distribution(options.alpha);
+            let mut cdf_w = Cdf::new(&pdf_w);
+            cdf_w.truncate(1.0 - AgcwdVariant::IMPROVED_MIN_GAMMA);
+            (cdf_w, false)
+        } else if t > AgcwdVariant::IMPROVED_THRESHOLD {
+            options.alpha = AgcwdVariant::IMPROVED_BRIGHT_ALPHA;
+            let pdf_w = pdf.reversed().to_weighting_distribution(options.alpha);
+            (Cdf::new(&pdf_w), true)
+        } else {
+            // A CDF of zeros makes all the gamma values `1.0` (i.e., the identity curve).
+            (Cdf(vec![0.0; pdf.0.len()]), false)
+        }
+    }
+}
+
+/// Intensity transformation curve computed by [`Agcwd`].
+///
+/// A curve maps each of the 256 input intensities to an enhanced intensity.
+/// The intensity of an RGB(A) pixel is derived by the [`IntensityModel`] of the curve,
+/// and that of a grayscale pixel is the gray level itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    table: [u8; 256],
+    model: IntensityModel,
+    exact_hue: bool,
+    alpha: f32,
+    fusion: f32,
+}
+
+impl Curve {
+    /// Returns the enhanced intensity corresponding to the given input intensity.
+    pub fn get(&self, intensity: u8) -> u8 {
+        self.table[usize::from(intensity)]
+    }
+
+    /// Returns the whole mapping table of this curve.
+    pub fn as_array(&self) -> &[u8; 256] {
+        &self.table
+    }
+
+    /// Returns the color model used to derive the intensities of RGB pixels.
+    pub fn intensity_model(&self) -> IntensityModel {
+        self.model
+    }
+
+    /// Returns the `alpha` used to compute this curve.
+    ///
+    /// This may differ from [`AgcwdOptions::alpha`] if [`AgcwdOptions::auto_alpha`] is enabled
+    /// or [`AgcwdVariant::Improved`] is used.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Returns the `fusion` used to compute this curve.
+    ///
+    /// This may differ from [`AgcwdOptions::fusion`] if [`AgcwdOptions::auto_fusion`] is enabled.
+    pub fn fusion(&self) -> f32 {
+        self.fusion
+    }
+
+    /// Applies this curve to a grayscale image.
+    pub fn apply_gray_image(&self, pixels: &mut [u8]) {
+        self.apply_image(pixels, PixelFormat::Gray);
+    }
+
+    /// Applies this curve to a grayscale image with an alpha channel.
+    pub fn apply_gray_alpha_image(&self, pixels: &mut [u8]) {
+        self.apply_image(pixels, PixelFormat::GrayAlpha);
+    }
+
+    /// Applies this curve to an RGB image.
+    pub fn apply_rgb_image(&self, pixels: &mut [u8]) {
+        self.apply_image(pixels, PixelFormat::Rgb);
+    }
+
+    /// Applies this curve to an RGBA image.
+    pub fn apply_rgba_image(&self, pixels: &mut [u8]) {
+        self.apply_image(pixels, PixelFormat::Rgba);
+    }
+
+    /// Applies this curve to a image having the given pixel format.
+    pub fn apply_image(&self, pixels: &mut [u8], format: PixelFormat) {
+        self.apply(ImageMut::new(pixels, format));
+    }
+
+    /// Applies this curve to an image, blending the original pixels and the enhanced ones by a mask.
+    ///
+    /// See [`Curve::apply_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn apply_image_with_mask(&self, pixels: &mut [u8], format: PixelFormat, mask: &[u8]) {
+        check_mask(mask, pixels.len() / format.channels());
+        self.apply(ImageMut::new(pixels, format).with_mask(Some(mask)));
+    }
+
+    /// Applies this curve to an image having the pixel format `src_format`,
+    /// writing the result to `dst` having the pixel format `dst_format` instead of modifying `src`.
+    ///
+    /// If the formats differ, the pixels are converted as follows:
+    /// - Colors are converted to gray levels by the [`IntensityModel`] of this curve
+    ///   (i.e., a gray level is the enhanced intensity of a pixel).
+    /// - Gray levels are enhanced by [`Curve::get()`] and then replicated to the red, green and blue channels.
+    /// - Alpha values are copied, or set to `255` if `src_format` has no alpha channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` and `dst` hold different numbers of pixels.
+    pub fn apply_image_into(
+        &self,
+        src: &[u8],
+        src_format: PixelFormat,
+        dst: &mut [u8],
+        dst_format: PixelFormat,
+    ) {
+        let pixels = src.len() / src_format.channels();
+        assert_eq!(
+            pixels,
+            dst.len() / dst_format.channels(),
+            "pixel count mismatch: src_len={}, dst_len={}",
+            src.len(),
+            dst.len()
+        );
+        if src_format == dst_format {
+            let len = pixels * src_format.channels();
+            dst[..len].copy_from_slice(&src[..len]);
+        } else {
+            src_format.convert(src, dst, dst_format, |r, g, b| {
+                self.model.intensity(r, g, b)
+            });
+            if src_format.is_grayscale() && !dst_format.is_grayscale() {
+                // The gray levels must be enhanced as such rather than as colors,
+                // which the intensity model may map differently.
+                let [r, g, b] = dst_format.color_offsets();
+                for p in dst.chunks_exact_mut(dst_format.channels()).take(pixels) {
+                    let v = self.get(p[r]);
+                    (p[r], p[g], p[b]) = (v, v, v);
+                }
+                return;
+            }
+        }
+        self.apply_image(dst, dst_format);
+    }
+
+    /// Applies this curve to the luminance samples of a YUV image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` is too short to hold a `width` x `height` image.
+    pub fn apply_yuv_image(
+        &self,
+        pixels: &mut [u8],
+        format: YuvFormat,
+        width: usize,
+        height: usize,
+    ) {
+        self.apply_image_view(&mut format.luma_view(pixels, width, height));
+    }
+
+    /// Applies this curve to an image view.
+    pub fn apply_image_view<B>(&self, image: &mut ImageView<B>)
+    where
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        self.apply(image.as_image_mut());
+    }
+
+    /// Applies this curve to an image view, blending the original pixels and the enhanced ones by a mask.
+    ///
+    /// `mask` holds a weight (from `0` to `255`) for each pixel in row-major order (without padding).
+    /// A weight of `0` leaves the pixel unchanged, `255` replaces it with the enhanced one,
+    /// and the other values linearly interpolate between them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn apply_image_view_with_mask<B>(&self, image: &mut ImageView<B>, mask: &[u8])
+    where
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        image.check_mask(mask);
+        self.apply(image.as_image_mut().with_mask(Some(mask)));
+    }
+
+    fn apply(&self, mut image: ImageMut<'_>) {
+        if image.format.is_grayscale() {
+            image.update_intensities(|v| self.get(v));
+            return;
+        }
+        if self.exact_hue {
+            let gains = color_format::GainTable::new(&self.table);
+            if self.model == IntensityModel::HsvValue && image.mask.is_none() {
+                let format = image.format;
+                image.for_each_row(|row| gains.scale_by_value(row, format));
+                return;
+            }
+            image.update_pixels(|r, g, b| {
+                let i = self.model.intensity(r, g, b);
+                gains.scale_rgb(r, g, b, i)
+            });
+        } else {
+            image.update_pixels(|r, g, b| self.model.apply(r, g, b, self));
+        }
+    }
+
+    fn new(cdf: &Cdf, options: &AgcwdOptions) -> Self {
+        let fusion = options.fusion;
+        let mut table = [0; 256];
+        for (i, x) in cdf.0.iter().copied().enumerate() {
+            let v0 = i as f32;
+            let v1 = 255.0 * (v0 / 255.0).powf(1.0 - x);
+            table[i] = (v0 * x * fusion + v1 * (1.0 - x * fusion)).round() as u8;
+        }
+        Self {
+            table,
+            model: options.intensity_model,
+            exact_hue: options.exact_hue,
+            alpha: options.alpha,
+            fusion,
+        }
+    }
+
+    /// Makes this curve map the negative of an intensity to the negative of its enhanced intensity.
+    fn invert(&mut self) {
+        let table = self.table;
+        for (i, y) in self.table.iter_mut().enumerate() {
+            *y = 255 - table[255 - i];
+        }
+    }
+
+    /// Returns the enhanced intensity corresponding to a fractional intensity by linear interpolation.
+    fn interpolate(&self, intensity: f32) -> f32 {
+        let v = intensity.clamp(0.0, 255.0);
+        let i = v.floor() as usize;
+        let lower = f32::from(self.table[i]);
+        let upper = f32::from(self.table[(i + 1).min(255)]);
+        lower + (upper - lower) * (v - i as f32)
+    }
+}
+
+/// Intensity transformation curve for 16-bit images computed by [`Agcwd`].
+///
+/// A curve maps each of the 65536 input intensities to an enhanced intensity.
+/// The CDF of the (possibly coarser) histogram is linearly interpolated between bins,
+/// so that the curve has no steps at the bin boundaries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve16 {
+    table: Vec<u16>,
+    alpha: f32,
+    fusion: f32,
+}
+
+impl Curve16 {
+    /// Returns the enhanced intensity corresponding to the given input intensity.
+    pub fn get(&self, intensity: u16) -> u16 {
+        self.table[usize::from(intensity)]
+    }
+
+    /// Returns the whole mapping table of this curve.
+    pub fn as_slice(&self) -> &[u16] {
+        &self.table
+    }
+
+    /// Returns the `alpha` used to compute this curve (see [`Curve::alpha()`]).
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Returns the `fusion` used to compute this curve (see [`Curve::fusion()`]).
+    pub fn fusion(&self) -> f32 {
+        self.fusion
+    }
+
+    /// Applies this curve to a 16-bit grayscale image.
+    pub fn apply_gray16_image(&self, pixels: &mut [u16]) {
+        self.apply_image(pixels, PixelFormat::Gray);
+    }
+
+    /// Applies this curve to a 16-bit grayscale image with an alpha channel.
+    pub fn apply_gray_alpha16_image(&self, pixels: &mut [u16]) {
+        self.apply_image(pixels, PixelFormat::GrayAlpha);
+    }
+
+    /// Applies this curve to a 16-bit RGB image.
+    pub fn apply_rgb16_image(&self, pixels: &mut [u16]) {
+        self.apply_image(pixels, PixelFormat::Rgb);
+    }
+
+    /// Applies this curve to a 16-bit RGBA image.
+    pub fn apply_rgba16_image(&self, pixels: &mut [u16]) {
+        self.apply_image(pixels, PixelFormat::Rgba);
+    }
+
+    /// Applies this curve to a 16-bit image having the given pixel format.
+    pub fn apply_image(&self, pixels: &mut [u16], format: PixelFormat) {
+        self.apply(ImageMut::new(pixels, format));
+    }
+
+    /// Applies this curve to a 16-bit image, blending the original pixels and the enhanced ones by a mask.
+    ///
+    /// See [`Curve::apply_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn apply_image_with_mask(&self, pixels: &mut [u16], format: PixelFormat, mask: &[u8]) {
+        check_mask(mask, pixels.len() / format.channels());
+        self.apply(ImageMut::new(pixels, format).with_mask(Some(mask)));
+    }
+
+    fn apply(&self, mut image: ImageMut<'_, u16>) {
+        if image.format.is_grayscale() {
+            image.update_intensities(|v| self.get(v));
+            return;
+        }
+        image.update_pixels(|r, g, b| {
+            let v = std::cmp::max(r, std::cmp::max(g, b));
+            color_format::scale_rgb16(r, g, b, v, self.get(v))
+        });
+    }
+
+    fn new(cdf: &Cdf, options: &AgcwdOptions) -> Self {
+        let fusion = options.fusion;
+        let bins = cdf.0.len();
+        let mut curve = vec![0; 65536];
+        for (i, y) in curve.iter_mut().enumerate() {
+            // The number of bins covered by the intensities from `0` to `i` (inclusive).
+            let x = cdf.interpolate((i + 1) as f32 * bins as f32 / 65536.0);
+
+            let v0 = i as f32;
+            let v1 = 65535.0 * (v0 / 65535.0).powf(1.0 - x);
+            *y = (v0 * x * fusion + v1 * (1.0 - x * fusion)).round() as u16;
+        }
+        Self {
+            table: curve,
+            alpha: options.alpha,
+            fusion,
+        }
+    }
+
+    /// Makes this curve map the negative of an intensity to the negative of its enhanced intensity.
+    fn invert(&mut self) {
+        self.table.reverse();
+        for y in &mut self.table {
+            *y = 65535 - *y;
+        }
+    }
+}
+
+/// Intensity transformation curve for floating-point images computed by [`Agcwd`].
+///
+/// Pixel values are expected to be non-negative and are usually normalized to the range from `0.0` to `1.0`.
+/// If an image contains larger values (e.g., HDR images), its intensities are normalized by
+/// the maximum intensity of the image instead.
+///
+/// Unlike [`Curve`] and [`Curve16`], this curve is not a lookup table:
+/// the gamma correction `v^(1 - cdf(v))` is evaluated analytically for each pixel,
+/// where `cdf(v)` is linearly interpolated between the histogram bins.
+#[derive(Debug, Clone)]
+pub struct CurveF32 {
+    cdf: Cdf,
+    alpha: f32,
+    fusion: f32,
+    scale: f32,
+
+    /// If `true`, the gamma correction is applied to the negative of a (normalized) intensity.
+    negative: bool,
+}
+
+impl CurveF32 {
+    /// Returns the enhanced intensity corresponding to the given input intensity.
+    ///
+    /// Intensities that are not positive or exceed the range of the analyzed image are returned unchanged.
+    pub fn get(&self, intensity: f32) -> f32 {
+        let v0 = intensity / self.scale;
+        if !(v0 > 0.0 && v0 < 1.0) {
+            return intensity;
+        }
+        if self.negative {
+            self.scale * (1.0 - self.correct(1.0 - v0))
+        } else {
+            self.scale * self.correct(v0)
+        }
+    }
+
+    /// Returns the `alpha` used to compute this curve (see [`Curve::alpha()`]).
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Returns the `fusion` used to compute this curve (see [`Curve::fusion()`]).
+    pub fn fusion(&self) -> f32 {
+        self.fusion
+    }
+
+    /// Applies the gamma correction to a normalized intensity.
+    fn correct(&self, v0: f32) -> f32 {
+        let x = self.cdf.interpolate(v0 * self.cdf.0.len() as f32);
+        let v1 = v0.powf(1.0 - x);
+        v0 * x * self.fusion + v1 * (1.0 - x * self.fusion)
+    }
+
+    /// Applies this curve to a floating-point grayscale image.
+    pub fn apply_gray_f32_image(&self, pixels: &mut [f32]) {
+        self.apply_image(pixels, PixelFormat::Gray);
+    }
+
+    /// Applies this curve to a floating-point grayscale image with an alpha channel.
+    pub fn apply_gray_alpha_f32_image(&self, pixels: &mut [f32]) {
+        self.apply_image(pixels, PixelFormat::GrayAlpha);
+    }
+
+    /// Applies this curve to a floating-point RGB image.
+    pub fn apply_rgb_f32_image(&self, pixels: &mut [f32]) {
+        self.apply_image(pixels, PixelFormat::Rgb);
+    }
+
+    /// Applies this curve to a floating-point RGBA image.
+    pub fn apply_rgba_f32_image(&self, pixels: &mut [f32]) {
+        self.apply_image(pixels, PixelFormat::Rgba);
+    }
+
+    /// Applies this curve to a floating-point image having the given pixel format.
+    pub fn apply_image(&self, pixels: &mut [f32], format: PixelFormat) {
+        self.apply(ImageMut::new(pixels, format));
+    }
+
+    /// Applies this curve to a floating-point image, blending the original pixels and the enhanced ones by a mask.
+    ///
+    /// See [`Curve::apply_image_view_with_mask()`] for the format of `mask`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `mask` differs from the number of pixels of the image.
+    pub fn apply_image_with_mask(&self, pixels: &mut [f32], format: PixelFormat, mask: &[u8]) {
+        check_mask(mask, pixels.len() / format.channels());
+        self.apply(ImageMut::new(pixels, format).with_mask(Some(mask)));
+    }
+
+    fn apply(&self, mut image: ImageMut<'_, f32>) {
+        if image.format.is_grayscale() {
+            image.update_intensities(|v| self.get(v));
+            return;
+        }
+        image.update_pixels(|r, g, b| {
+            let v = max(r, max(g, b));
+            if v > 0.0 {
+                let scale = self.get(v) / v;
+                (r * scale, g * scale, b * scale)
+            } else {
+                (r, g, b)
+            }
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Geometry {
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl Geometry {
+    fn flat(len: usize, format: PixelFormat) -> Self {
+        let width = len / format.channels();
+        Self {
+            width,
+            height: 1,
+            stride: width * format.channels(),
+        }
+    }
+
+    /// Splits an image having this geometry into disjoint parts to be processed in parallel.
+    ///
+    /// `len` is the number of samples of the image (the last row may lack the padding).
+    /// The parts are in order, and the samples of each part follow those of the previous one.
+    #[cfg(feature = "rayon")]
+    fn split(self, channels: usize, len: usize) -> Vec<Part> {
+        let Self {
+            width,
+            height,
+            stride,
+        } = self;
+        let mut parts = Vec::new();
+        if height == 1 {
+            for x in (0..width).step_by(PARALLEL_CHUNK_PIXELS) {
+                let w = PARALLEL_CHUNK_PIXELS.min(width - x);
+                parts.push(Part {
+                    geometry: Self {
+                        width: w,
+                        height: 1,
+                        stride: w * channels,
+                    },
+                    samples: x * channels..(x + w) * channels,
+                    mask: x..x + w,
+                });
+            }
+        } else {
+            let rows = (PARALLEL_CHUNK_PIXELS / width.max(1)).max(1);
+            for y in (0..height).step_by(rows) {
+                let h = rows.min(height - y);
+                parts.push(Part {
+                    geometry: Self {
+                        width,
+                        height: h,
+                        stride,
+                    },
+                    samples: y * stride..((y + h) * stride).min(len),
+                    mask: y * width..(y + h) * width,
+                });
+            }
+        }
+        parts
+    }
+}
+
+/// Part of an image returned by [`Geometry::split()`].
+#[cfg(feature = "rayon")]
+#[derive(Debug)]
+struct Part {
+    geometry: Geometry,
+
+    /// Range of the samples of the part in those of the image.
+    samples: std::ops::Range<usize>,
+
+    /// Range of the weights of the part in the mask of the image.
+    mask: std::ops::Range<usize>,
+}
+
+/// Panics if the length of a mask differs from the number of pixels of an image.
+fn check_mask(mask: &[u8], pixels: usize) {
+    assert_eq!(
+        mask.len(),
+        pixels,
+        "mask length mismatch: mask_len={}, pixels={pixels}",
+        mask.len()
+    );
+}
+
+/// Number of pixels processed by a task when the `rayon` feature is enabled.
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK_PIXELS: usize = 1 << 16;
+
+trait Sample: Copy + PartialOrd + Send + Sync {
+    /// Blends `original` and `enhanced` by `weight / 255`.
+    fn blend(original: Self, enhanced: Self, weight: u8) -> Self;
+}
+
+impl Sample for u8 {
+    fn blend(original: Self, enhanced: Self, weight: u8) -> Self {
+        let w = u32::from(weight);
+        ((u32::from(original) * (255 - w) + u32::from(enhanced) * w + 127) / 255) as u8
+    }
+}
+
+impl Sample for u16 {
+    fn blend(original: Self, enhanced: Self, weight: u8) -> Self {
+        let w = u32::from(weight);
+        ((u32::from(original) * (255 - w) + u32::from(enhanced) * w + 127) / 255) as u16
+    }
+}
+
+impl Sample for f32 {
+    fn blend(original: Self, enhanced: Self, weight: u8) -> Self {
+        let w = f32::from(weight) / 255.0;
+        original + (enhanced - original) * w
+    }
+}
+
+#[derive(Debug)]
+struct Image<'a, T = u8> {
+    pixels: &'a [T],
+    format: PixelFormat,
+    geometry: Geometry,
+
+    /// Per-pixel weights (`0..=255`) in row-major order without padding.
+    mask: Option<&'a [u8]>,
+}
+
+impl<'a, T: Sample> Image<'a, T> {
+    fn new(pixels: &'a [T], format: PixelFormat) -> Self {
+        Self::with_geometry(pixels, format, Geometry::flat(pixels.len(), format))
+    }
+
+    fn with_geometry(pixels: &'a [T], format: PixelFormat, geometry: Geometry) -> Self {
+        Self {
+            pixels,
+            format,
+            geometry,
+            mask: None,
         }
     }
-}
 
-/// [`Agcwd`] provides methods to enhance image contrast based on the [AGCWD] algorithm.
-///
-/// [AGCWD]: https://ieeexplore.ieee.org/abstract/document/6336819/
-#[derive(Debug, Default)]
-pub struct Agcwd {
-    options: AgcwdOptions,
-}
+    fn with_mask(mut self, mask: Option<&'a [u8]>) -> Self {
+        self.mask = mask;
+        self
+    }
 
-impl Agcwd {
-    /// Makes a new [`Agcwd`] instance with the default options.
-    pub fn new() -> Self {
-        Self::with_options(Default::default())
+    fn rows(&self) -> impl '_ + Iterator<Item = &'_ [T]> {
+        let row_len = self.geometry.width * self.format.channels();
+        self.pixels
+            .chunks(self.geometry.stride.max(1))
+            .take(self.geometry.height)
+            .map(move |row| &row[..row_len])
     }
 
-    /// Makes a new [`Agcwd`] instance with the given options.
-    pub fn with_options(options: AgcwdOptions) -> Self {
-        Self { options }
+    fn pixels(&self) -> impl '_ + Iterator<Item = &'_ [T]> {
+        let channels = self.format.channels();
+        self.rows().flat_map(move |row| row.chunks_exact(channels))
     }
 
-    /// Enhances the contrast of an RGB image.
-    pub fn enhance_rgb_image(&self, pixels: &mut [u8]) {
-        self.enhance_image::<3>(pixels);
+    fn colors(&self) -> impl '_ + Iterator<Item = (T, T, T)> {
+        let [r, g, b] = self.format.color_offsets();
+        self.pixels().map(move |p| (p[r], p[g], p[b]))
     }
 
-    /// Enhances the contrast of an RGBA image.
-    pub fn enhance_rgba_image(&self, pixels: &mut [u8]) {
-        self.enhance_image::<4>(pixels);
+    fn intensities(&self) -> impl '_ + Iterator<Item = T> {
+        self.colors().map(|(r, g, b)| max(r, max(g, b)))
     }
 
-    fn enhance_image<const N: usize>(&self, pixels: &mut [u8]) {
-        let mut image = Image::<N>::new(pixels);
-        let pdf = Pdf::new(&image);
-        let pdf_w = pdf.to_weighting_distribution(self.options.alpha);
-        let cdf_w = Cdf::new(&pdf_w);
-        let curve = IntensityTransformationCurve::new(&cdf_w, self.options.fusion);
-        image.update_pixels(|r, g, b| {
-            let (h, s, v) = color_format::rgb_to_hsv(r, g, b);
-            color_format::hsv_to_rgb(h, s, curve.0[usize::from(v)])
-        });
+    fn weights(&self) -> impl '_ + Iterator<Item = u8> {
+        let mask = self.mask.into_iter().flatten().copied();
+        mask.chain(std::iter::repeat(u8::MAX))
+    }
+
+    /// Counts the pixels for each bin index returned by `f`.
+    ///
+    /// If this image has a mask, each pixel is counted by its weight.
+    fn histogram<F>(&self, bins: usize, f: F) -> Vec<usize>
+    where
+        F: Fn(T, T, T) -> usize + Sync,
+    {
+        self.count(bins, |image, histogram| {
+            if image.mask.is_none() {
+                for (r, g, b) in image.colors() {
+                    histogram[f(r, g, b)] += 1;
+                }
+                return;
+            }
+            for ((r, g, b), w) in image.colors().zip(image.weights()) {
+                if w != 0 {
+                    histogram[f(r, g, b)] += usize::from(w);
+                }
+            }
+        })
+    }
+
+    /// Builds a histogram by calling `count` for each part of this image.
+    fn count<F>(&self, bins: usize, count: F) -> Vec<usize>
+    where
+        F: Fn(&Self, &mut [usize]) + Sync,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            self.split()
+                .into_par_iter()
+                .map(|part| {
+                    let mut histogram = vec![0; bins];
+                    count(&part, &mut histogram);
+                    histogram
+                })
+                .reduce(
+                    || vec![0; bins],
+                    |mut a, b| {
+                        for (x, y) in a.iter_mut().zip(b) {
+                            *x += y;
+                        }
+                        a
+                    },
+                )
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut histogram = vec![0; bins];
+            count(self, &mut histogram);
+            histogram
+        }
+    }
+
+    /// Splits this image into disjoint parts to be processed in parallel.
+    #[cfg(feature = "rayon")]
+    fn split(&self) -> Vec<Self> {
+        let channels = self.format.channels();
+        self.geometry
+            .split(channels, self.pixels.len())
+            .into_iter()
+            .map(|part| {
+                Self::with_geometry(&self.pixels[part.samples], self.format, part.geometry)
+                    .with_mask(self.mask.map(|m| &m[part.mask]))
+            })
+            .collect()
     }
 }
 
-#[derive(Debug)]
-struct IntensityTransformationCurve([u8; 256]);
+impl Image<'_> {
+    /// Counts the pixels for each intensity derived by `model`.
+    fn intensity_histogram(&self, model: IntensityModel) -> Vec<usize> {
+        if model == IntensityModel::HsvValue || self.format.is_grayscale() {
+            self.value_histogram()
+        } else {
+            self.histogram(256, |r, g, b| usize::from(model.intensity(r, g, b)))
+        }
+    }
 
-impl IntensityTransformationCurve {
-    fn new(cdf: &Cdf, fusion: f32) -> Self {
-        let mut curve = [0; 256];
-        for (i, x) in cdf.0.iter().copied().enumerate() {
-            let v0 = i as f32;
-            let v1 = 255.0 * (v0 / 255.0).powf(1.0 - x);
-            curve[i] = (v0 * x * fusion + v1 * (1.0 - x * fusion)).round() as u8;
+    /// Counts the pixels for each HSV value (i.e., `max(R, G, B)`).
+    fn value_histogram(&self) -> Vec<usize> {
+        if self.format.is_grayscale() || self.mask.is_some() {
+            return self.histogram(256, |r, g, b| usize::from(max(r, max(g, b))));
         }
-        Self(curve)
+
+        const BLOCK_PIXELS: usize = 1024;
+        let channels = self.format.channels();
+        self.count(256, |image, histogram| {
+            let mut values = [0; BLOCK_PIXELS];
+            for row in image.rows() {
+                for block in row.chunks(BLOCK_PIXELS * channels) {
+                    let values = &mut values[..block.len() / channels];
+                    simd::max3(block, image.format, values);
+                    for &v in values.iter() {
+                        histogram[usize::from(v)] += 1;
+                    }
+                }
+            }
+        })
     }
 }
 
 #[derive(Debug)]
-struct Image<'a, const N: usize> {
-    pixels: &'a mut [u8],
-    size: usize,
+struct ImageMut<'a, T = u8> {
+    pixels: &'a mut [T],
+    format: PixelFormat,
+    geometry: Geometry,
+
+    /// Per-pixel weights (`0..=255`) in row-major order without padding.
+    mask: Option<&'a [u8]>,
 }
 
-impl<'a, const N: usize> Image<'a, N> {
-    fn new(pixels: &'a mut [u8]) -> Self {
-        let size = pixels.len() / N;
-        Self { pixels, size }
+impl<'a, T: Sample> ImageMut<'a, T> {
+    fn new(pixels: &'a mut [T], format: PixelFormat) -> Self {
+        let geometry = Geometry::flat(pixels.len(), format);
+        Self::with_geometry(pixels, format, geometry)
+    }
+
+    fn with_geometry(pixels: &'a mut [T], format: PixelFormat, geometry: Geometry) -> Self {
+        Self {
+            pixels,
+            format,
+            geometry,
+            mask: None,
+        }
+    }
+
+    fn with_mask(mut self, mask: Option<&'a [u8]>) -> Self {
+        self.mask = mask;
+        self
     }
 
-    fn intensities(&self) -> impl '_ + Iterator<Item = u8> {
+    fn rows_mut(&mut self) -> impl '_ + Iterator<Item = &'_ mut [T]> {
+        let row_len = self.geometry.width * self.format.channels();
         self.pixels
-            .chunks_exact(N)
-            .map(|p| std::cmp::max(p[0], std::cmp::max(p[1], p[2])))
+            .chunks_mut(self.geometry.stride.max(1))
+            .take(self.geometry.height)
+            .map(move |row| &mut row[..row_len])
+    }
+
+    fn pixels_mut(&mut self) -> impl '_ + Iterator<Item = &'_ mut [T]> {
+        let channels = self.format.channels();
+        self.rows_mut()
+            .flat_map(move |row| row.chunks_exact_mut(channels))
+    }
+
+    /// Calls `f` for each row of pixels (without padding), ignoring the mask.
+    fn for_each_row<F>(&mut self, f: F)
+    where
+        F: Fn(&mut [T]) + Sync,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            self.split().into_par_iter().for_each(|mut part| {
+                part.rows_mut().for_each(&f);
+            });
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.rows_mut().for_each(f);
+        }
     }
 
-    fn len(&self) -> usize {
-        self.size
+    /// Updates the pixels and blends them with the original ones by the weights of the mask, if any.
+    fn update_intensities<F>(&mut self, f: F)
+    where
+        F: Fn(T) -> T + Sync,
+    {
+        let [i, _, _] = self.format.color_offsets();
+        self.for_each_pixel(|p, w| match w {
+            0 => {}
+            u8::MAX => p[i] = f(p[i]),
+            _ => p[i] = T::blend(p[i], f(p[i]), w),
+        });
     }
 
+    /// Updates the pixels and blends them with the original ones by the weights of the mask, if any.
     fn update_pixels<F>(&mut self, f: F)
     where
-        F: Fn(u8, u8, u8) -> (u8, u8, u8),
+        F: Fn(T, T, T) -> (T, T, T) + Sync,
+    {
+        let [r, g, b] = self.format.color_offsets();
+        self.for_each_pixel(|p, w| {
+            if w == 0 {
+                return;
+            }
+            let rgb = f(p[r], p[g], p[b]);
+            if w == u8::MAX {
+                p[r] = rgb.0;
+                p[g] = rgb.1;
+                p[b] = rgb.2;
+            } else {
+                p[r] = T::blend(p[r], rgb.0, w);
+                p[g] = T::blend(p[g], rgb.1, w);
+                p[b] = T::blend(p[b], rgb.2, w);
+            }
+        });
+    }
+
+    fn for_each_pixel<F>(&mut self, f: F)
+    where
+        F: Fn(&mut [T], u8) + Sync,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            self.split().into_par_iter().for_each(|mut part| {
+                part.for_each_pixel_sequential(&f);
+            });
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.for_each_pixel_sequential(&f);
+        }
+    }
+
+    fn for_each_pixel_sequential<F>(&mut self, f: &F)
+    where
+        F: Fn(&mut [T], u8),
     {
-        for p in self.pixels.chunks_exact_mut(N) {
-            let rgb = f(p[0], p[1], p[2]);
-            p[0] = rgb.0;
-            p[1] = rgb.1;
-            p[2] = rgb.2;
+        let mask = self.mask;
+        let weights = mask.into_iter().flatten().copied();
+        let weights = weights.chain(std::iter::repeat(u8::MAX));
+        for (p, w) in self.pixels_mut().zip(weights) {
+            f(p, w);
         }
     }
+
+    /// Splits this image into disjoint parts to be processed in parallel.
+    #[cfg(feature = "rayon")]
+    fn split(&mut self) -> Vec<ImageMut<'_, T>> {
+        let channels = self.format.channels();
+        let parts = self.geometry.split(channels, self.pixels.len());
+        let mut rest = &mut *self.pixels;
+        parts
+            .into_iter()
+            .map(|part| {
+                let (pixels, tail) = std::mem::take(&mut rest).split_at_mut(part.samples.len());
+                rest = tail;
+                ImageMut::with_geometry(pixels, self.format, part.geometry)
+                    .with_mask(self.mask.map(|m| &m[part.mask]))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
-struct Pdf([f32; 256]);
+struct Pdf(Vec<f32>);
 
 impl Pdf {
-    fn new<const N: usize>(image: &Image<'_, N>) -> Self {
-        let mut histogram = [0; 256];
-        for intensity in image.intensities() {
-            histogram[usize::from(intensity)] += 1;
+    fn new(image: &Image<'_>, model: IntensityModel) -> Self {
+        Self::from_histogram(image.intensity_histogram(model))
+    }
+
+    fn new16(image: &Image<'_, u16>, bins: usize) -> Self {
+        let histogram = image.histogram(bins, |r, g, b| {
+            usize::from(max(r, max(g, b))) * bins / 65536
+        });
+        Self::from_histogram(histogram)
+    }
+
+    fn new_f32(image: &Image<'_, f32>, bins: usize, scale: f32) -> Self {
+        let histogram = image.histogram(bins, |r, g, b| {
+            // NOTE: Negative and NaN values are counted in the first bin.
+            let b = (max(r, max(g, b)) / scale * bins as f32) as usize;
+            b.min(bins - 1)
+        });
+        Self::from_histogram(histogram)
+    }
+
+    fn from_histogram(histogram: Vec<usize>) -> Self {
+        let n = histogram.iter().sum::<usize>() as f32;
+        if n == 0.0 {
+            // No pixels are counted (e.g., an all-zero mask); the zero PDF gives the identity curve.
+            return Self(vec![0.0; histogram.len()]);
         }
+        Self(histogram.into_iter().map(|c| c as f32 / n).collect())
+    }
 
-        let mut pdf = [0.0; 256];
-        let n = image.len() as f32;
-        for (i, c) in histogram.into_iter().enumerate() {
-            pdf[i] = c as f32 / n;
+    fn blend(&mut self, other: &Self, decay: f32) {
+        for (x, y) in self.0.iter_mut().zip(other.0.iter().copied()) {
+            *x = *x * decay + y * (1.0 - decay);
         }
-        Self(pdf)
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        let bc: f32 = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(x, y)| (x * y).sqrt())
+            .sum();
+        (1.0 - bc.min(1.0)).sqrt()
+    }
+
+    /// Returns the mean intensity normalized to the range from `0.0` to `1.0`.
+    fn mean(&self) -> f32 {
+        (self.stats().mean / self.max_bin()) as f32
+    }
+
+    /// Returns the standard deviation of the intensities normalized to the range from `0.0` to `1.0`.
+    fn std_dev(&self) -> f32 {
+        (self.stats().std_dev / self.max_bin()) as f32
+    }
+
+    /// Returns the Shannon entropy in bits.
+    fn entropy(&self) -> f32 {
+        self.stats().entropy as f32
+    }
+
+    fn stats(&self) -> histogram::Stats {
+        histogram::Stats::new(self.0.iter().map(|&p| f64::from(p)))
+    }
+
+    fn max_bin(&self) -> f64 {
+        (self.0.len() - 1).max(1) as f64
+    }
+
+    /// Returns the PDF of the negative image.
+    fn reversed(&self) -> Self {
+        Self(self.0.iter().rev().copied().collect())
     }
 
     fn to_weighting_distribution(&self, alpha: f32) -> Self {
@@ -160,7 +1781,7 @@ impl Pdf {
             min_intensity = min_intensity.min(x);
         }
 
-        let mut pdf_w = self.0;
+        let mut pdf_w = self.0.clone();
         let range = max_intensity - min_intensity + f32::EPSILON;
         for x in &mut pdf_w {
             *x = max_intensity * ((*x - min_intensity) / range).powf(alpha);
@@ -169,22 +1790,49 @@ impl Pdf {
     }
 }
 
-#[derive(Debug)]
-struct Cdf([f32; 256]);
+#[derive(Debug, Clone)]
+struct Cdf(Vec<f32>);
 
 impl Cdf {
     fn new(pdf: &Pdf) -> Self {
-        let mut cdf = [0.0; 256];
+        let mut cdf = vec![0.0; pdf.0.len()];
         let mut sum = 0.0;
         for (i, x) in pdf.0.iter().copied().enumerate() {
             sum += x;
             cdf[i] = sum;
         }
+        if sum == 0.0 {
+            // A uniform histogram has an all-zero weighting distribution; the zero CDF leaves the image unchanged.
+            return Self(cdf);
+        }
         for x in &mut cdf {
             *x /= sum;
         }
         Self(cdf)
     }
+
+    /// Limits the CDF values to `max` (i.e., limits the gamma values to `1.0 - max` or more).
+    fn truncate(&mut self, max: f32) {
+        for x in &mut self.0 {
+            *x = x.min(max);
+        }
+    }
+
+    /// Returns the CDF value after `t` bins, linearly interpolated within a bin.
+    fn interpolate(&self, t: f32) -> f32 {
+        let bins = self.0.len();
+        let b = (t.ceil() as usize).clamp(1, bins) - 1;
+        let lower = if b == 0 { 0.0 } else { self.0[b - 1] };
+        lower + (self.0[b] - lower) * (t - b as f32)
+    }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if a < b {
+        b
+    } else {
+        a
+    }
 }
 
 #[cfg(test)]
@@ -204,4 +1852,642 @@ mod tests {
         let agcwd = Agcwd::new();
         agcwd.enhance_rgba_image(&mut pixels);
     }
+
+    #[test]
+    fn enhance_gray_image_works() {
+        let mut pixels = [1, 2, 3, 4, 5, 6];
+        let agcwd = Agcwd::new();
+        agcwd.enhance_gray_image(&mut pixels);
+    }
+
+    #[test]
+    fn enhance_gray_alpha_image_works() {
+        let mut pixels = [10, 255, 20, 128, 30, 0];
+        let agcwd = Agcwd::new();
+        agcwd.enhance_gray_alpha_image(&mut pixels);
+        assert_eq!([pixels[1], pixels[3], pixels[5]], [255, 128, 0]);
+    }
+
+    #[test]
+    fn enhance_rgb16_image_works() {
+        let mut pixels = [100, 200, 300, 4000, 5000, 6000, 60000, 30000, 0];
+        let agcwd = Agcwd::new();
+        agcwd.enhance_rgb16_image(&mut pixels);
+    }
+
+    #[test]
+    fn curve16_is_consistent_with_curve() {
+        let pixels = [1, 2, 3, 40, 50, 60, 200, 100, 0];
+        let pixels16 = pixels.map(|v| u16::from(v) * 257);
+        let agcwd = Agcwd::with_options(AgcwdOptions {
+            histogram_bins: 65536,
+            ..Default::default()
+        });
+        let curve = agcwd.compute_rgb_curve(&pixels);
+        let curve16 = agcwd.compute_rgb16_curve(&pixels16);
+        for v in [0, 1, 3, 40, 60, 128, 200, 255] {
+            let expected = i32::from(curve.get(v));
+            let actual = (i32::from(curve16.get(u16::from(v) * 257)) + 128) / 257;
+            assert!((expected - actual).abs() <= 1, "v={v}");
+        }
+    }
+
+    #[test]
+    fn enhance_rgb_f32_image_works() {
+        let mut pixels = [0.01, 0.02, 0.03, 0.4, 0.5, 0.6, 0.0, 0.0, 0.0];
+        let agcwd = Agcwd::new();
+        agcwd.enhance_rgb_f32_image(&mut pixels);
+        assert!(pixels[2] > 0.03);
+        assert!((pixels[1] / pixels[2] - 0.02 / 0.03).abs() < 1e-6);
+        assert_eq!(&pixels[6..], [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn masks_work_with_8_bit_16_bit_and_f32_images() {
+        let agcwd = Agcwd::new();
+        let original = [10u8, 20, 30, 40, 200, 250];
+        let mask = [255, 255, 255, 128, 0, 0];
+        let mut pixels = original;
+        let mut image = ImageView::new(&mut pixels[..], PixelFormat::Gray, 3, 2, 3);
+        agcwd.enhance_image_view_with_mask(&mut image, &mask);
+        let curve = agcwd.compute_curve_with_mask(&original, PixelFormat::Gray, &mask);
+        let mut flat = original;
+        curve.apply_image_with_mask(&mut flat, PixelFormat::Gray, &mask);
+        assert_eq!(flat, pixels);
+        let mut flat = original;
+        agcwd.enhance_image_with_mask(&mut flat, PixelFormat::Gray, &mask);
+        assert_eq!(flat, pixels);
+        assert_eq!(pixels[4..], original[4..]);
+
+        let original = [1000u16, 3000, 5000, 7000, 60000, 65000];
+        let curve = agcwd.compute_curve16_with_mask(&original, PixelFormat::Gray, &mask);
+        assert_ne!(curve, agcwd.compute_curve16(&original, PixelFormat::Gray));
+        let mut pixels = original;
+        agcwd.enhance_image16_with_mask(&mut pixels, PixelFormat::Gray, &mask);
+        assert_eq!(pixels[..3], [0, 1, 2].map(|i| curve.get(original[i])));
+        assert_eq!(pixels[3], u16::blend(7000, curve.get(7000), 128));
+        assert_eq!(pixels[4..], original[4..]);
+
+        let original = [0.1f32, 0.2, 0.3, 0.4, 0.9, 1.0];
+        let mut pixels = original;
+        let curve = agcwd.compute_curve_f32_with_mask(&original, PixelFormat::Gray, &mask);
+        agcwd.enhance_image_f32_with_mask(&mut pixels, PixelFormat::Gray, &mask);
+        assert_eq!(pixels[..3], [0, 1, 2].map(|i| curve.get(original[i])));
+        assert_eq!(pixels[3], f32::blend(0.4, curve.get(0.4), 128));
+        assert_eq!(pixels[4..], original[4..]);
+
+        // Pixels ignored by the mask do not affect the scale of the curve.
+        let hdr = [0.1f32, 0.2, 0.3, 0.4, 8.0, 16.0];
+        let curve = agcwd.compute_curve_f32_with_mask(&hdr, PixelFormat::Gray, &mask);
+        let masked = [0.1f32, 0.2, 0.3, 0.4];
+        let expected = agcwd.compute_curve_f32_with_mask(&masked, PixelFormat::Gray, &mask[..4]);
+        for v in masked {
+            assert_eq!(curve.get(v), expected.get(v));
+        }
+
+        // An all-zero mask gives the identity curve.
+        let mut pixels = original;
+        agcwd.enhance_image_f32_with_mask(&mut pixels, PixelFormat::Gray, &[0; 6]);
+        assert_eq!(pixels, original);
+    }
+
+    #[test]
+    fn enhance_hdr_gray_f32_image_works() {
+        let mut pixels = [0.5, 1.0, 2.0, 4.0];
+        let agcwd = Agcwd::new();
+        agcwd.enhance_gray_f32_image(&mut pixels);
+        assert!(pixels.iter().all(|&v| v > 0.0 && v <= 4.0));
+        assert_eq!(pixels[3], 4.0);
+    }
+
+    #[test]
+    fn enhance_image_follows_pixel_format() {
+        let rgba = [1, 2, 3, 255, 40, 50, 60, 128, 200, 100, 0, 0];
+        let agcwd = Agcwd::new();
+
+        let mut expected = rgba;
+        agcwd.enhance_rgba_image(&mut expected);
+
+        let swizzles = [
+            (PixelFormat::Bgra, [2, 1, 0, 3]),
+            (PixelFormat::Argb, [3, 0, 1, 2]),
+            (PixelFormat::Abgr, [3, 2, 1, 0]),
+        ];
+        for (format, order) in swizzles {
+            let swizzle = |pixels: &[u8]| -> Vec<u8> {
+                pixels
+                    .chunks_exact(4)
+                    .flat_map(|p| order.map(|i| p[i]))
+                    .collect()
+            };
+            let mut pixels = swizzle(&rgba);
+            agcwd.enhance_image(&mut pixels, format);
+            assert_eq!(pixels, swizzle(&expected), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn intensity_models_work() {
+        let models = [
+            IntensityModel::HsvValue,
+            IntensityModel::Bt601Luma,
+            IntensityModel::Bt709Luma,
+            IntensityModel::HslLightness,
+            IntensityModel::CielabLightness,
+        ];
+        // A dark image having moderately saturated colors (the brightest ones get clipped when enhanced).
+        let original = (0..64)
+            .flat_map(|i| [10 + i, 20 + i / 2, 15 + i % 7 * 3])
+            .collect::<Vec<u8>>();
+        let mut results = Vec::new();
+        for intensity_model in models {
+            let agcwd = Agcwd::with_options(AgcwdOptions {
+                intensity_model,
+                ..Default::default()
+            });
+            let curve = agcwd.compute_rgb_curve(&original);
+            let mut pixels = original.clone();
+            agcwd.enhance_rgb_image(&mut pixels);
+
+            let (mut brightened, mut unclipped) = (0, 0);
+            for (o, e) in original.chunks_exact(3).zip(pixels.chunks_exact(3)) {
+                let before = intensity_model.intensity(o[0], o[1], o[2]);
+                let after = intensity_model.intensity(e[0], e[1], e[2]);
+                // The intensity of a pixel is mapped by the curve
+                // (up to rounding, as some models interpolate the curve at fractional intensities).
+                let lower = curve.get(before.saturating_sub(1)).saturating_sub(1);
+                let upper = curve.get(before.saturating_add(1)).saturating_add(1);
+                assert!(
+                    (lower..=upper).contains(&after),
+                    "{intensity_model:?}: {o:?} -> {e:?}"
+                );
+                brightened += usize::from(after > before);
+                unclipped += usize::from(!e.contains(&255));
+
+                let k = match intensity_model {
+                    IntensityModel::Bt601Luma => color_format::BT601,
+                    IntensityModel::Bt709Luma => color_format::BT709,
+                    _ => continue,
+                };
+                if e.contains(&255) {
+                    continue;
+                }
+                // The luma models change only Y, so Cb and Cr are kept up to rounding (unless clipped).
+                let (_, cb0, cr0) = color_format::rgb_to_ycbcr(o[0], o[1], o[2], k);
+                let (_, cb1, cr1) = color_format::rgb_to_ycbcr(e[0], e[1], e[2], k);
+                assert!(
+                    (cb0 - cb1).abs() < 1.0 && (cr0 - cr1).abs() < 1.0,
+                    "{intensity_model:?}: {o:?} -> {e:?}"
+                );
+            }
+            assert_eq!(brightened, original.len() / 3, "{intensity_model:?}");
+            assert!(unclipped > original.len() / 6, "{intensity_model:?}");
+            results.push(pixels);
+
+            let mut gray = [1, 40, 200];
+            agcwd.enhance_gray_image(&mut gray);
+            let mut expected = [1, 40, 200];
+            Agcwd::new().enhance_gray_image(&mut expected);
+            assert_eq!(gray, expected);
+        }
+
+        // HSL and CIELAB change the colors differently from HSV.
+        assert_ne!(results[3], results[0]);
+        assert_ne!(results[4], results[0]);
+    }
+
+    #[test]
+    fn exact_hue_differs_little_from_hsv_round_trip() {
+        let colors = (0..=255)
+            .step_by(5)
+            .flat_map(|r| (0..=255).step_by(5).map(move |g| (r, g)))
+            .flat_map(|(r, g)| (0..=255).step_by(5).flat_map(move |b| [r, g, b]))
+            .collect::<Vec<u8>>();
+        let sources = [
+            (0..3000).map(|i| (i * 7 % 80) as u8).collect::<Vec<_>>(),
+            (0..3000).map(|i| (i * 7 % 256) as u8).collect(),
+            (0..3000).map(|i| (150 + i * 7 % 106) as u8).collect(),
+        ];
+        for source in sources {
+            let curve = Agcwd::new().compute_rgb_curve(&source);
+            let exact = Curve {
+                exact_hue: true,
+                ..curve.clone()
+            };
+            let mut round_trip = colors.clone();
+            curve.apply_rgb_image(&mut round_trip);
+            let mut scaled = colors.clone();
+            exact.apply_rgb_image(&mut scaled);
+
+            let diffs = round_trip.iter().zip(&scaled).map(|(a, b)| a.abs_diff(*b));
+            assert!(diffs.clone().max().unwrap() <= 7);
+            let mean = diffs.map(f64::from).sum::<f64>() / colors.len() as f64;
+            assert!(mean < 1.0);
+        }
+    }
+
+    #[test]
+    fn exact_hue_with_identity_curve_preserves_pixels() {
+        let models = [
+            IntensityModel::HsvValue,
+            IntensityModel::Bt601Luma,
+            IntensityModel::CielabLightness,
+        ];
+        for model in models {
+            let curve = Curve {
+                table: std::array::from_fn(|i| i as u8),
+                model,
+                exact_hue: true,
+                alpha: 0.5,
+                fusion: 0.0,
+            };
+            let original = (0..=255)
+                .step_by(5)
+                .flat_map(|r| (0..=255).step_by(5).map(move |g| (r, g)))
+                .flat_map(|(r, g)| (0..=255).step_by(5).flat_map(move |b| [r, g, b]))
+                .collect::<Vec<_>>();
+            let mut pixels = original.clone();
+            curve.apply_rgb_image(&mut pixels);
+            assert!(pixels == original, "{model:?}");
+        }
+    }
+
+    #[test]
+    fn exact_hue_preserves_channel_ratios() {
+        let agcwd = Agcwd::with_options(AgcwdOptions {
+            exact_hue: true,
+            ..Default::default()
+        });
+        let original = [10, 20, 40, 40, 50, 60, 200, 100, 0];
+        let mut pixels = original;
+        agcwd.enhance_rgb_image(&mut pixels);
+        for (p0, p1) in original.chunks_exact(3).zip(pixels.chunks_exact(3)) {
+            let v0 = f32::from(p0[0].max(p0[1]).max(p0[2]));
+            let v1 = f32::from(p1[0].max(p1[1]).max(p1[2]));
+            for (c0, c1) in p0.iter().zip(p1.iter()) {
+                assert!((f32::from(*c1) - f32::from(*c0) * v1 / v0).abs() <= 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn enhance_large_image_works() {
+        let original = (0..300 * 300 * 3)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect::<Vec<_>>();
+        let agcwd = Agcwd::new();
+        let curve = agcwd.compute_rgb_curve(&original);
+
+        let mut expected = original.clone();
+        for pixels in expected.chunks_mut(3 * 1000) {
+            curve.apply_rgb_image(pixels);
+        }
+
+        let mut pixels = original.clone();
+        agcwd.enhance_rgb_image(&mut pixels);
+        assert!(pixels == expected);
+
+        let mut pixels = original;
+        let mut image = ImageView::new(&mut pixels[..], PixelFormat::Rgb, 300, 300, 900);
+        agcwd.enhance_image_view(&mut image);
+        assert!(pixels == expected);
+    }
+
+    #[test]
+    fn large_image_results_do_not_depend_on_features() {
+        // Larger than `PARALLEL_CHUNK_PIXELS`, so that the images are split when `rayon` is enabled.
+        let (width, height) = (400, 300);
+        let original = (0..width * height * 3)
+            .map(|i| (i * 7 % 251 / 2) as u8)
+            .collect::<Vec<_>>();
+        let mask = (0..width * height)
+            .map(|i| (i % 256) as u8)
+            .collect::<Vec<_>>();
+
+        // The references are computed without splitting the images:
+        // the histograms are counted pixel by pixel, and the curves are applied to chunks
+        // smaller than `PARALLEL_CHUNK_PIXELS`.
+        const CHUNK: usize = 1000;
+        fn count<T: Copy>(
+            pixels: &[T],
+            weights: &[u8],
+            bins: usize,
+            bin: impl Fn(T, T, T) -> usize,
+        ) -> Pdf {
+            let mut histogram = vec![0; bins];
+            for (p, &w) in pixels.chunks_exact(3).zip(weights) {
+                histogram[bin(p[0], p[1], p[2])] += usize::from(w);
+            }
+            Pdf::from_histogram(histogram)
+        }
+        let no_mask = vec![1; width * height];
+
+        for (options, bin) in [
+            (
+                AgcwdOptions::default(),
+                (|r, g, b| usize::from(max(r, max(g, b)))) as fn(u8, u8, u8) -> usize,
+            ),
+            (
+                AgcwdOptions {
+                    intensity_model: IntensityModel::Bt601Luma,
+                    exact_hue: true,
+                    ..Default::default()
+                },
+                |r, g, b| usize::from(IntensityModel::Bt601Luma.intensity(r, g, b)),
+            ),
+        ] {
+            let agcwd = Agcwd::with_options(options);
+            let curve = agcwd.curve_from_pdf(&count(&original, &no_mask, 256, bin));
+            let mut expected = original.clone();
+            for pixels in expected.chunks_mut(3 * CHUNK) {
+                curve.apply_rgb_image(pixels);
+            }
+
+            let mut pixels = original.clone();
+            agcwd.enhance_rgb_image(&mut pixels);
+            assert!(pixels == expected);
+        }
+
+        let agcwd = Agcwd::new();
+        let pdf = count(&original, &mask, 256, |r, g, b| {
+            usize::from(max(r, max(g, b)))
+        });
+        let curve = agcwd.curve_from_pdf(&pdf);
+        let mut expected = original.clone();
+        for (pixels, mask) in expected.chunks_mut(3 * CHUNK).zip(mask.chunks(CHUNK)) {
+            let mut image = ImageView::new(pixels, PixelFormat::Rgb, CHUNK, 1, CHUNK * 3);
+            curve.apply_image_view_with_mask(&mut image, mask);
+        }
+        let mut pixels = original.clone();
+        let mut image = ImageView::new(&mut pixels[..], PixelFormat::Rgb, width, height, width * 3);
+        agcwd.enhance_image_view_with_mask(&mut image, &mask);
+        assert!(pixels == expected);
+
+        let original16 = original
+            .iter()
+            .map(|&v| u16::from(v) * 257)
+            .collect::<Vec<_>>();
+        let pdf = count(&original16, &no_mask, 4096, |r, g, b| {
+            usize::from(max(r, max(g, b))) * 4096 / 65536
+        });
+        let curve = agcwd.curve16_from_pdf(&pdf);
+        let mut expected = original16.clone();
+        for pixels in expected.chunks_mut(3 * CHUNK) {
+            curve.apply_rgb16_image(pixels);
+        }
+        let mut pixels = original16;
+        agcwd.enhance_rgb16_image(&mut pixels);
+        assert!(pixels == expected);
+
+        let original_f32 = original
+            .iter()
+            .map(|&v| f32::from(v) / 255.0)
+            .collect::<Vec<_>>();
+        let scale = original_f32.iter().copied().fold(1.0, f32::max);
+        let pdf = count(&original_f32, &no_mask, 4096, |r, g, b| {
+            ((max(r, max(g, b)) / scale * 4096.0) as usize).min(4095)
+        });
+        let curve = agcwd.curve_f32_from_pdf(&pdf, scale);
+        let mut expected = original_f32.clone();
+        for pixels in expected.chunks_mut(3 * CHUNK) {
+            curve.apply_rgb_f32_image(pixels);
+        }
+        let mut pixels = original_f32;
+        agcwd.enhance_rgb_f32_image(&mut pixels);
+        assert!(pixels == expected);
+    }
+
+    #[test]
+    fn try_methods_reject_invalid_inputs() {
+        let agcwd = Agcwd::new();
+        assert_eq!(
+            agcwd.try_enhance_image(&mut [0, 1, 2, 3], PixelFormat::Rgb),
+            Err(AgcwdError::InvalidLength {
+                len: 4,
+                format: PixelFormat::Rgb
+            })
+        );
+        assert_eq!(
+            agcwd.try_compute_curve16(&[], PixelFormat::Gray),
+            Err(AgcwdError::EmptyImage)
+        );
+
+        let options = AgcwdOptions {
+            alpha: -1.0,
+            ..Default::default()
+        };
+        assert!(Agcwd::try_with_options(options.clone()).is_err());
+        assert!(Agcwd::with_options(options)
+            .try_compute_curve_f32(&[0.5], PixelFormat::Gray)
+            .is_err());
+        let options = AgcwdOptions {
+            fusion: f32::NAN,
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+
+        let options = AgcwdOptions {
+            histogram_bins: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            Agcwd::with_options(options).try_enhance_image16(&mut [0, 1000], PixelFormat::Gray),
+            Err(AgcwdError::InvalidOption {
+                name: "histogram_bins",
+                value: 1.0
+            })
+        );
+
+        let mut yuv = [0; 5];
+        assert_eq!(
+            agcwd.try_enhance_yuv_image(&mut yuv, YuvFormat::I420, 2, 2),
+            Err(AgcwdError::BufferTooShort {
+                len: 5,
+                required: 6
+            })
+        );
+        let mut image = ImageView::new(&mut yuv[..], PixelFormat::Gray, 0, 1, 0);
+        assert_eq!(
+            agcwd.try_enhance_image_view(&mut image),
+            Err(AgcwdError::EmptyImage)
+        );
+
+        let mut pixels = [0, 1, 2, 3, 4, 5];
+        agcwd
+            .try_enhance_image(&mut pixels, PixelFormat::Rgb)
+            .unwrap();
+        let mut expected = [0, 1, 2, 3, 4, 5];
+        agcwd.enhance_rgb_image(&mut expected);
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn uniform_histogram_leaves_image_unchanged() {
+        let agcwd = Agcwd::new();
+        let ramp = (0..=255).collect::<Vec<u8>>();
+        let mut pixels = ramp.clone();
+        agcwd
+            .try_enhance_image(&mut pixels, PixelFormat::Gray)
+            .unwrap();
+        assert_eq!(pixels, ramp);
+
+        let ramp = (0..=u16::MAX).collect::<Vec<u16>>();
+        let mut pixels = ramp.clone();
+        agcwd
+            .try_enhance_image16(&mut pixels, PixelFormat::Gray)
+            .unwrap();
+        assert_eq!(pixels, ramp);
+    }
+
+    #[test]
+    fn improved_variant_works() {
+        let agcwd = Agcwd::with_options(AgcwdOptions {
+            variant: AgcwdVariant::Improved,
+            ..Default::default()
+        });
+
+        // Bright images are darkened.
+        let mut pixels = [180, 200, 220, 240, 250, 255];
+        agcwd.enhance_gray_image(&mut pixels);
+        assert!(pixels[..5]
+            .iter()
+            .zip([180, 200, 220, 240, 250])
+            .all(|(&a, b)| a <= b));
+        assert!(pixels[..5]
+            .iter()
+            .zip([180, 200, 220, 240, 250])
+            .any(|(&a, b)| a < b));
+        assert_eq!(pixels[5], 255);
+
+        // Dimmed images are brightened less than by the original algorithm.
+        let dimmed = [0, 10, 20, 30, 40, 50];
+        let mut pixels = dimmed;
+        agcwd.enhance_gray_image(&mut pixels);
+        let mut original = dimmed;
+        Agcwd::new().enhance_gray_image(&mut original);
+        assert!(pixels.iter().zip(dimmed).all(|(&a, b)| a >= b));
+        assert!(pixels.iter().zip(original).all(|(&a, b)| a <= b));
+
+        // Others are left unchanged.
+        let mut pixels = [60, 90, 120, 150];
+        agcwd.enhance_gray_image(&mut pixels);
+        assert_eq!(pixels, [60, 90, 120, 150]);
+
+        // The 16-bit and floating-point paths classify images in the same way.
+        let mut pixels = [180 * 257, 200 * 257, 220 * 257, 240 * 257];
+        agcwd.enhance_gray16_image(&mut pixels);
+        assert!(pixels[0] < 180 * 257);
+        let mut pixels = [0.7, 0.8, 0.9, 0.95];
+        agcwd.enhance_gray_f32_image(&mut pixels);
+        assert!(pixels[0] < 0.7);
+    }
+
+    #[test]
+    fn auto_alpha_and_fusion_work() {
+        let agcwd = Agcwd::with_options(AgcwdOptions {
+            auto_alpha: true,
+            auto_fusion: true,
+            ..Default::default()
+        });
+
+        // A narrow dark histogram.
+        let curve = agcwd.compute_gray_curve(&[10, 10, 11, 12, 12, 12]);
+        assert!(curve.alpha() > 0.6);
+        assert_eq!(curve.fusion(), 0.0);
+
+        // A widely spread bright histogram.
+        let pixels = (0..=255).chain(200..=255).collect::<Vec<u8>>();
+        let curve = agcwd.compute_gray_curve(&pixels);
+        assert!(curve.alpha() < 0.4);
+        assert!(curve.fusion() > 0.0);
+
+        // The selected values are used.
+        let options = AgcwdOptions {
+            alpha: curve.alpha(),
+            fusion: curve.fusion(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Agcwd::with_options(options).compute_gray_curve(&pixels),
+            curve
+        );
+
+        // Manual values are reported as they are.
+        let curve = Agcwd::new().compute_rgb16_curve(&[0, 1000, 2000]);
+        assert_eq!((curve.alpha(), curve.fusion()), (0.5, 0.0));
+    }
+
+    #[test]
+    fn enhance_image_into_works() {
+        let agcwd = Agcwd::new();
+        let src = [1, 2, 3, 40, 50, 60, 200, 100, 0];
+
+        let mut expected = src;
+        agcwd.enhance_rgb_image(&mut expected);
+        let mut dst = [0; 9];
+        agcwd.enhance_rgb_image_into(&src, &mut dst);
+        assert_eq!(dst, expected);
+
+        let mut rgba = [0; 12];
+        agcwd.enhance_image_into(&src, PixelFormat::Rgb, &mut rgba, PixelFormat::Rgba);
+        assert_eq!(rgba[..3], expected[..3]);
+        assert_eq!(rgba[3], 255);
+        assert_eq!(rgba[4..7], expected[3..6]);
+
+        let mut gray = [0; 3];
+        agcwd.enhance_image_into(&src, PixelFormat::Rgb, &mut gray, PixelFormat::Gray);
+        let curve = agcwd.compute_rgb_curve(&src);
+        assert_eq!(gray, [curve.get(3), curve.get(60), curve.get(200)]);
+
+        let mut bgra = [0; 12];
+        agcwd.enhance_image_into(&gray, PixelFormat::Gray, &mut bgra, PixelFormat::Bgra);
+        let v = agcwd.compute_gray_curve(&gray).get(gray[0]);
+        assert_eq!(bgra[..4], [v, v, v, 255]);
+    }
+
+    #[test]
+    fn enhance_gray_image_into_color_image_works() {
+        let gray = [10, 20, 30, 200];
+        for model in [IntensityModel::CielabLightness, IntensityModel::Bt601Luma] {
+            let agcwd = Agcwd::with_options(AgcwdOptions {
+                intensity_model: model,
+                ..Default::default()
+            });
+            let mut expected = gray;
+            agcwd.enhance_gray_image(&mut expected);
+
+            let mut rgb = [0; 12];
+            agcwd.enhance_image_into(&gray, PixelFormat::Gray, &mut rgb, PixelFormat::Rgb);
+            let expected_rgb = expected.iter().flat_map(|&v| [v; 3]).collect::<Vec<_>>();
+            assert_eq!(rgb[..], expected_rgb, "{model:?}");
+        }
+    }
+
+    #[test]
+    fn too_few_histogram_bins_are_clamped() {
+        let pixels = [1000, 2000, 3000, 60000];
+        let curve = |bins| {
+            Agcwd::with_options(AgcwdOptions {
+                histogram_bins: bins,
+                ..Default::default()
+            })
+            .compute_gray16_curve(&pixels)
+        };
+        assert_eq!(curve(1).as_slice(), curve(2).as_slice());
+        assert_ne!(curve(1).get(2000), 0);
+    }
+
+    #[test]
+    fn compute_and_apply_curve_works() {
+        let original = [1, 2, 3, 40, 50, 60, 200, 100, 0];
+        let agcwd = Agcwd::new();
+        let curve = agcwd.compute_rgb_curve(&original);
+
+        let mut applied = original;
+        curve.apply_rgb_image(&mut applied);
+
+        let mut enhanced = original;
+        agcwd.enhance_rgb_image(&mut enhanced);
+        assert_eq!(applied, enhanced);
+    }
 }
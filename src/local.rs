@@ -0,0 +1,346 @@
+use crate::color_format::GainTable;
+use crate::{Agcwd, AgcwdError, AgcwdOptions, Curve, ImageView, Pdf, PixelFormat};
+
+/// [`AgcwdLocal`] options.
+#[derive(Debug, Clone)]
+pub struct AgcwdLocalOptions {
+    /// Options of the underlying AGCWD algorithm.
+    pub agcwd: AgcwdOptions,
+
+    /// Number of tiles in the horizontal direction.
+    ///
+    /// The value is clamped to the range from `1` to the width of an image,
+    /// and the fallible (`try_`) methods reject `0`.
+    ///
+    /// Defaults to `8`.
+    pub tile_columns: usize,
+
+    /// Number of tiles in the vertical direction.
+    ///
+    /// The value is clamped to the range from `1` to the height of an image,
+    /// and the fallible (`try_`) methods reject `0`.
+    ///
+    /// Defaults to `8`.
+    pub tile_rows: usize,
+}
+
+impl Default for AgcwdLocalOptions {
+    fn default() -> Self {
+        Self {
+            agcwd: AgcwdOptions::default(),
+            tile_columns: 8,
+            tile_rows: 8,
+        }
+    }
+}
+
+impl AgcwdLocalOptions {
+    /// Checks whether the options have valid values.
+    pub fn validate(&self) -> Result<(), AgcwdError> {
+        self.agcwd.validate()?;
+        if self.tile_columns == 0 {
+            return Err(AgcwdError::InvalidOption {
+                name: "tile_columns",
+                value: 0.0,
+            });
+        }
+        if self.tile_rows == 0 {
+            return Err(AgcwdError::InvalidOption {
+                name: "tile_rows",
+                value: 0.0,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// [`AgcwdLocal`] enhances an image using a curve per tile.
+///
+/// An image is divided into a grid of tiles and a curve is computed from the histogram of each tile.
+/// Each pixel is then enhanced by bilinearly interpolating the results of the curves of the four nearest tiles
+/// (as CLAHE does), so that no seams appear at the tile boundaries.
+///
+/// Unlike [`Agcwd`], this can enhance images having both bright and dark regions (e.g., backlit scenes).
+#[derive(Debug)]
+pub struct AgcwdLocal {
+    agcwd: Agcwd,
+    columns: usize,
+    rows: usize,
+}
+
+impl AgcwdLocal {
+    /// Makes a new [`AgcwdLocal`] instance with the default options.
+    pub fn new() -> Self {
+        Self::with_options(Default::default())
+    }
+
+    /// Makes a new [`AgcwdLocal`] instance with the given options.
+    pub fn with_options(options: AgcwdLocalOptions) -> Self {
+        Self {
+            agcwd: Agcwd::with_options(options.agcwd),
+            columns: options.tile_columns,
+            rows: options.tile_rows,
+        }
+    }
+
+    /// Makes a new [`AgcwdLocal`] instance with the given options, returning an error if they are invalid.
+    pub fn try_with_options(options: AgcwdLocalOptions) -> Result<Self, AgcwdError> {
+        options.validate()?;
+        Ok(Self::with_options(options))
+    }
+
+    /// Enhances the contrast of a grayscale image having the given size.
+    pub fn enhance_gray_image(&self, pixels: &mut [u8], width: usize, height: usize) {
+        self.enhance_image(pixels, PixelFormat::Gray, width, height);
+    }
+
+    /// Enhances the contrast of an RGB image having the given size.
+    pub fn enhance_rgb_image(&self, pixels: &mut [u8], width: usize, height: usize) {
+        self.enhance_image(pixels, PixelFormat::Rgb, width, height);
+    }
+
+    /// Enhances the contrast of an RGBA image having the given size.
+    pub fn enhance_rgba_image(&self, pixels: &mut [u8], width: usize, height: usize) {
+        self.enhance_image(pixels, PixelFormat::Rgba, width, height);
+    }
+
+    /// Enhances the contrast of an image having the given pixel format and size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` is shorter than `width * height` pixels.
+    pub fn enhance_image(
+        &self,
+        pixels: &mut [u8],
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+    ) {
+        let stride = width * format.channels();
+        self.enhance_image_view(&mut ImageView::new(pixels, format, width, height, stride));
+    }
+
+    /// Enhances the contrast of an image having the given pixel format and size, returning an error if the input is invalid.
+    ///
+    /// Unlike [`AgcwdLocal::enhance_image()`], this fails if `pixels` is too short,
+    /// the image has no pixels, or the options are invalid.
+    pub fn try_enhance_image(
+        &self,
+        pixels: &mut [u8],
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+    ) -> Result<(), AgcwdError> {
+        let stride = width * format.channels();
+        let mut image = ImageView::try_new(pixels, format, width, height, stride)?;
+        self.agcwd.options.validate()?;
+        if width == 0 || height == 0 {
+            return Err(AgcwdError::EmptyImage);
+        }
+        self.enhance_image_view(&mut image);
+        Ok(())
+    }
+
+    /// Enhances the contrast of an image view.
+    pub fn enhance_image_view<B>(&self, image: &mut ImageView<B>)
+    where
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 {
+            return;
+        }
+        let columns = self.columns.clamp(1, width);
+        let rows = self.rows.clamp(1, height);
+
+        let mut tiles = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            let (y0, y1) = (row * height / rows, (row + 1) * height / rows);
+            for column in 0..columns {
+                let (x0, x1) = (column * width / columns, (column + 1) * width / columns);
+                let tile = image.sub_view(x0, y0, x1 - x0, y1 - y0);
+                let pdf = Pdf::new(&tile.as_image(), self.agcwd.options.intensity_model);
+                tiles.push(Tile::new(self.agcwd.curve_from_pdf(&pdf)));
+            }
+        }
+
+        let xs = neighbors(width, columns);
+        let ys = neighbors(height, rows);
+        let format = image.format();
+        let channels = format.channels();
+        let [r, g, b] = format.color_offsets();
+        let apply_row = |y: usize, row: &mut [u8]| {
+            let (r0, r1, fy) = ys[y];
+            for (x, p) in row[..width * channels]
+                .chunks_exact_mut(channels)
+                .enumerate()
+            {
+                let (c0, c1, fx) = xs[x];
+                let neighbors = [
+                    (&tiles[r0 * columns + c0], (1.0 - fy) * (1.0 - fx)),
+                    (&tiles[r0 * columns + c1], (1.0 - fy) * fx),
+                    (&tiles[r1 * columns + c0], fy * (1.0 - fx)),
+                    (&tiles[r1 * columns + c1], fy * fx),
+                ];
+                if format.is_grayscale() {
+                    let mut v = 0.0;
+                    for (tile, w) in neighbors {
+                        v += w * f32::from(tile.curve.get(p[r]));
+                    }
+                    p[r] = v.round() as u8;
+                } else {
+                    let mut rgb = (0.0, 0.0, 0.0);
+                    for (tile, w) in neighbors {
+                        if w == 0.0 {
+                            continue;
+                        }
+                        let enhanced = tile.apply(p[r], p[g], p[b]);
+                        rgb.0 += w * f32::from(enhanced.0);
+                        rgb.1 += w * f32::from(enhanced.1);
+                        rgb.2 += w * f32::from(enhanced.2);
+                    }
+                    p[r] = rgb.0.round() as u8;
+                    p[g] = rgb.1.round() as u8;
+                    p[b] = rgb.2.round() as u8;
+                }
+            }
+        };
+
+        let stride = image.stride();
+        let pixels = image.as_image_mut().pixels;
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            pixels
+                .par_chunks_mut(stride)
+                .take(height)
+                .enumerate()
+                .for_each(|(y, row)| apply_row(y, row));
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (y, row) in pixels.chunks_mut(stride).take(height).enumerate() {
+                apply_row(y, row);
+            }
+        }
+    }
+}
+
+impl Default for AgcwdLocal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+struct Tile {
+    curve: Curve,
+    gains: Option<GainTable>,
+}
+
+impl Tile {
+    fn new(curve: Curve) -> Self {
+        let gains = curve.exact_hue.then(|| GainTable::new(&curve.table));
+        Self { curve, gains }
+    }
+
+    fn apply(&self, r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+        if let Some(gains) = &self.gains {
+            gains.scale_rgb(r, g, b, self.curve.model.intensity(r, g, b))
+        } else {
+            self.curve.model.apply(r, g, b, &self.curve)
+        }
+    }
+}
+
+/// Returns the two nearest tiles and the interpolation weight of the second one for each pixel position.
+fn neighbors(len: usize, tiles: usize) -> Vec<(usize, usize, f32)> {
+    let tile_len = len as f32 / tiles as f32;
+    (0..len)
+        .map(|i| {
+            // Position relative to the center of the first tile, in tiles.
+            let t = (i as f32 + 0.5) / tile_len - 0.5;
+            let t0 = (t.floor().max(0.0) as usize).min(tiles - 1);
+            let t1 = (t0 + 1).min(tiles - 1);
+            (t0, t1, (t - t0 as f32).clamp(0.0, 1.0))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_tile_is_equivalent_to_global_enhancement() {
+        let original = [
+            1, 2, 3, 40, 50, 60, 200, 100, 0, 9, 9, 9, 70, 80, 90, 5, 5, 5,
+        ];
+        let options = AgcwdLocalOptions {
+            tile_columns: 1,
+            tile_rows: 1,
+            ..Default::default()
+        };
+
+        let mut local = original;
+        AgcwdLocal::with_options(options).enhance_rgb_image(&mut local, 3, 2);
+
+        let mut global = original;
+        Agcwd::new().enhance_rgb_image(&mut global);
+        assert_eq!(local, global);
+    }
+
+    #[test]
+    fn invalid_inputs_are_rejected() {
+        let options = AgcwdLocalOptions {
+            tile_rows: 0,
+            ..Default::default()
+        };
+        assert!(AgcwdLocal::try_with_options(options).is_err());
+
+        let local = AgcwdLocal::new();
+        assert_eq!(
+            local.try_enhance_image(&mut [0; 5], PixelFormat::Gray, 3, 2),
+            Err(AgcwdError::BufferTooShort {
+                len: 5,
+                required: 6
+            })
+        );
+        assert_eq!(
+            local.try_enhance_image(&mut [], PixelFormat::Gray, 0, 0),
+            Err(AgcwdError::EmptyImage)
+        );
+    }
+
+    #[test]
+    fn dark_and_bright_regions_are_enhanced_separately() {
+        // The left half is dark and the right half is bright.
+        let (width, height) = (64, 8);
+        let original = (0..width * height)
+            .map(|i| {
+                let x = i % width;
+                if x < width / 2 {
+                    (x % 8 * 4) as u8
+                } else {
+                    (192 + x % 8 * 8) as u8
+                }
+            })
+            .collect::<Vec<_>>();
+        let options = AgcwdLocalOptions {
+            tile_columns: 4,
+            tile_rows: 1,
+            ..Default::default()
+        };
+
+        let mut local = original.clone();
+        AgcwdLocal::with_options(options).enhance_gray_image(&mut local, width, height);
+
+        let mut global = original.clone();
+        Agcwd::new().enhance_gray_image(&mut global);
+
+        // The dark pixels far from the bright region are brightened more than by the global curve.
+        let dark = |pixels: &[u8]| pixels[..8].iter().map(|&v| u32::from(v)).sum::<u32>();
+        assert!(dark(&local) > dark(&global));
+    }
+}
@@ -0,0 +1,320 @@
+//! Image quality metrics to evaluate contrast enhancement.
+//!
+//! The metrics are computed from the intensities of the pixels, except for [`psnr()`] which uses the color components.
+//! [`ambe()`] and [`entropy()`] use the same intensities as [`Agcwd`](crate::Agcwd)
+//! with the default options (i.e., [`IntensityModel::HsvValue`]),
+//! while [`eme()`] and [`ssim()`] use the BT.601 luma as they are usually computed on grayscale images.
+//!
+//! Formats having an alpha channel are supported, but alpha values are ignored.
+//!
+//! [`eme()`] and [`ssim()`] take the width of an image in pixels, and ignore the pixels after the last complete row.
+//! Images having no complete rows (including a `width` of `0`) are regarded as empty.
+use crate::{Histogram, Image, IntensityModel, PixelFormat};
+
+/// Computes the absolute mean brightness error (AMBE) between an original image and its enhanced image.
+///
+/// Smaller values mean that the enhancement preserves the brightness better.
+///
+/// # Panics
+///
+/// Panics if the lengths of `original` and `enhanced` differ.
+pub fn ambe(original: &[u8], enhanced: &[u8], format: PixelFormat) -> f32 {
+    check_lengths(original, enhanced);
+    let mean = |pixels| Histogram::new(pixels, format, IntensityModel::HsvValue).mean();
+    (mean(original) - mean(enhanced)).abs()
+}
+
+/// Computes the discrete entropy of the intensities of an image in bits (from `0.0` to `8.0`).
+///
+/// Larger values mean that the image has more details.
+/// Compare the values of an original image and its enhanced image to evaluate the enhancement.
+pub fn entropy(pixels: &[u8], format: PixelFormat) -> f32 {
+    Histogram::new(pixels, format, IntensityModel::HsvValue).entropy()
+}
+
+/// Computes the measure of enhancement (EME) of an image.
+///
+/// The image is divided into `block_size` x `block_size` blocks (the blocks at the right and bottom edges may be smaller),
+/// and EME is the average of `20 * ln((max + 1) / (min + 1))` over the blocks,
+/// where `max` and `min` are the maximum and minimum intensities in a block
+/// (`1` is added to avoid division by zero).
+///
+/// Larger values mean that the image has higher local contrast.
+/// Compare the values of an original image and its enhanced image to evaluate the enhancement.
+/// An empty image gives `0.0`.
+///
+/// # Panics
+///
+/// Panics if `block_size` is `0`.
+pub fn eme(pixels: &[u8], format: PixelFormat, width: usize, block_size: usize) -> f32 {
+    assert!(block_size > 0, "block size must be positive");
+    let intensities = luma(pixels, format);
+    let height = rows(intensities.len(), width);
+    if height == 0 {
+        return 0.0;
+    }
+    let intensities = &intensities[..height * width];
+
+    let mut sum = 0.0;
+    let mut blocks = 0;
+    for y in (0..height).step_by(block_size) {
+        for x in (0..width).step_by(block_size) {
+            let (mut min, mut max) = (u8::MAX, u8::MIN);
+            for row in intensities[y * width..]
+                .chunks_exact(width)
+                .take(block_size)
+            {
+                for &v in &row[x..(x + block_size).min(width)] {
+                    min = min.min(v);
+                    max = max.max(v);
+                }
+            }
+            sum += 20.0 * ((f64::from(max) + 1.0) / (f64::from(min) + 1.0)).ln();
+            blocks += 1;
+        }
+    }
+    (sum / f64::from(blocks)) as f32
+}
+
+/// Computes the peak signal-to-noise ratio (PSNR) between an original image and its enhanced image in decibels.
+///
+/// The mean squared error is computed over the R, G and B components (or the gray levels) of all the pixels.
+/// Larger values mean that the enhanced image is closer to the original image,
+/// and identical images give [`f32::INFINITY`].
+///
+/// # Panics
+///
+/// Panics if the lengths of `original` and `enhanced` differ.
+pub fn psnr(original: &[u8], enhanced: &[u8], format: PixelFormat) -> f32 {
+    check_lengths(original, enhanced);
+    let original = Image::new(original, format);
+    let enhanced = Image::new(enhanced, format);
+    let samples = if format.is_grayscale() { 1 } else { 3 };
+
+    let mut sum = 0.0;
+    let mut n = 0;
+    for (a, b) in original.colors().zip(enhanced.colors()) {
+        let a = [a.0, a.1, a.2];
+        let b = [b.0, b.1, b.2];
+        for (&a, &b) in a.iter().zip(b.iter()).take(samples) {
+            sum += (f64::from(a) - f64::from(b)).powi(2);
+        }
+        n += samples;
+    }
+    if sum == 0.0 {
+        return f32::INFINITY;
+    }
+    let mse = sum / n as f64;
+    (10.0 * (255.0 * 255.0 / mse).log10()) as f32
+}
+
+/// Computes the structural similarity index (SSIM) between an original image and its enhanced image.
+///
+/// SSIM is the average of the local indices computed over all the `8` x `8` windows of the luma of the images
+/// (if an image is smaller than a window, the whole image is regarded as a window).
+/// The values range up to `1.0`, and larger values mean that the structures of the original image are better preserved.
+/// Empty images give `1.0`.
+///
+/// # Panics
+///
+/// Panics if the lengths of `original` and `enhanced` differ.
+pub fn ssim(original: &[u8], enhanced: &[u8], format: PixelFormat, width: usize) -> f32 {
+    const WINDOW: usize = 8;
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    check_lengths(original, enhanced);
+    let channels = format.channels();
+    let height = rows(original.len() / channels, width);
+    if height == 0 {
+        return 1.0;
+    }
+    let window_width = WINDOW.min(width);
+    let window_height = WINDOW.min(height);
+
+    // The values of x, y, x^2, y^2 and xy of the pixels in a row.
+    let row = |i: usize| {
+        let range = i * width * channels..(i + 1) * width * channels;
+        let x = luma(&original[range.clone()], format);
+        let y = luma(&enhanced[range], format);
+        x.into_iter().zip(y).map(|(a, b)| {
+            let (a, b) = (f64::from(a), f64::from(b));
+            [a, b, a * a, b * b, a * b]
+        })
+    };
+    let add = |s: &mut [f64; 5], v: &[f64; 5], sign: f64| {
+        for (s, v) in s.iter_mut().zip(v) {
+            *s += sign * v;
+        }
+    };
+
+    // Sums of the values over the last `window_height` rows for each column,
+    // so that the memory grows with the width (rather than the size) of the images.
+    // The sums are of integers and thus exact.
+    let mut columns = vec![[0.0f64; 5]; width];
+    let n = (window_width * window_height) as f64;
+    let mut sum = 0.0;
+    for i in 0..height {
+        for (c, v) in columns.iter_mut().zip(row(i)) {
+            add(c, &v, 1.0);
+        }
+        if i >= window_height {
+            for (c, v) in columns.iter_mut().zip(row(i - window_height)) {
+                add(c, &v, -1.0);
+            }
+        }
+        if i + 1 < window_height {
+            continue;
+        }
+
+        let mut s = [0.0f64; 5];
+        for j in 0..width {
+            add(&mut s, &columns[j], 1.0);
+            if j >= window_width {
+                add(&mut s, &columns[j - window_width], -1.0);
+            }
+            if j + 1 < window_width {
+                continue;
+            }
+            let (mx, my) = (s[0] / n, s[1] / n);
+            let vx = (s[2] / n - mx * mx).max(0.0);
+            let vy = (s[3] / n - my * my).max(0.0);
+            let cov = s[4] / n - mx * my;
+            sum += ((2.0 * mx * my + C1) * (2.0 * cov + C2))
+                / ((mx * mx + my * my + C1) * (vx + vy + C2));
+        }
+    }
+    let windows = (height - window_height + 1) * (width - window_width + 1);
+    (sum / windows as f64) as f32
+}
+
+fn luma(pixels: &[u8], format: PixelFormat) -> Vec<u8> {
+    let image = Image::new(pixels, format);
+    if format.is_grayscale() {
+        return image.colors().map(|(v, _, _)| v).collect();
+    }
+    image
+        .colors()
+        .map(|(r, g, b)| IntensityModel::Bt601Luma.intensity(r, g, b))
+        .collect()
+}
+
+/// Returns the number of complete rows of `width` pixels in an image having `pixels` pixels.
+fn rows(pixels: usize, width: usize) -> usize {
+    pixels.checked_div(width).unwrap_or(0)
+}
+
+fn check_lengths(original: &[u8], enhanced: &[u8]) {
+    assert_eq!(
+        original.len(),
+        enhanced.len(),
+        "image length mismatch: original_len={}, enhanced_len={}",
+        original.len(),
+        enhanced.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agcwd;
+
+    #[test]
+    fn identical_images_work() {
+        let pixels = (0..16 * 16 * 3)
+            .map(|i| (i * 7 % 256) as u8)
+            .collect::<Vec<_>>();
+        let format = PixelFormat::Rgb;
+        assert_eq!(ambe(&pixels, &pixels, format), 0.0);
+        assert_eq!(psnr(&pixels, &pixels, format), f32::INFINITY);
+        assert!((ssim(&pixels, &pixels, format, 16) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn enhanced_images_work() {
+        let original = (0..16 * 16)
+            .flat_map(|i| {
+                let v = (i % 16 * 2 + i / 16) as u8;
+                [v, v / 2, v / 3, 255]
+            })
+            .collect::<Vec<_>>();
+        let mut enhanced = original.clone();
+        Agcwd::new().enhance_rgba_image(&mut enhanced);
+        let format = PixelFormat::Rgba;
+
+        assert!(ambe(&original, &enhanced, format) > 0.0);
+        assert!(eme(&enhanced, format, 16, 4) > eme(&original, format, 16, 4));
+        let psnr = psnr(&original, &enhanced, format);
+        assert!(psnr.is_finite() && psnr > 0.0);
+        let ssim = ssim(&original, &enhanced, format, 16);
+        assert!(ssim > 0.0 && ssim < 1.0);
+
+        let flat = [10; 16];
+        assert_eq!(entropy(&flat, PixelFormat::Gray), 0.0);
+        assert_eq!(eme(&flat, PixelFormat::Gray, 4, 2), 0.0);
+    }
+
+    #[test]
+    fn ssim_is_average_of_windows() {
+        let (width, height) = (19, 13);
+        let x = (0..width * height)
+            .map(|i| (i * 37 % 251) as u8)
+            .collect::<Vec<_>>();
+        let y = x.iter().map(|&v| v / 2 + 60).collect::<Vec<_>>();
+
+        let (c1, c2) = (6.5025, 58.5225);
+        let mut sum = 0.0;
+        for i in 0..=height - 8 {
+            for j in 0..=width - 8 {
+                let window = |p: &[u8]| {
+                    (i..i + 8)
+                        .flat_map(|i| p[i * width + j..][..8].to_vec())
+                        .map(f64::from)
+                        .collect::<Vec<_>>()
+                };
+                let (a, b) = (window(&x), window(&y));
+                let mean = |v: &[f64]| v.iter().sum::<f64>() / 64.0;
+                let (mx, my) = (mean(&a), mean(&b));
+                let cov = |u: &[f64], mu: f64, v: &[f64], mv: f64| {
+                    u.iter()
+                        .zip(v)
+                        .map(|(u, v)| (u - mu) * (v - mv))
+                        .sum::<f64>()
+                        / 64.0
+                };
+                let (vx, vy, cxy) = (
+                    cov(&a, mx, &a, mx),
+                    cov(&b, my, &b, my),
+                    cov(&a, mx, &b, my),
+                );
+                sum += ((2.0 * mx * my + c1) * (2.0 * cxy + c2))
+                    / ((mx * mx + my * my + c1) * (vx + vy + c2));
+            }
+        }
+        let expected = sum / ((height - 7) * (width - 7)) as f64;
+        let actual = ssim(&x, &y, PixelFormat::Gray, width);
+        assert!(
+            (f64::from(actual) - expected).abs() < 1e-6,
+            "{actual} vs {expected}"
+        );
+    }
+
+    #[test]
+    fn incomplete_rows_are_ignored() {
+        let pixels = (0..10).map(|i| i * 20).collect::<Vec<u8>>();
+        let format = PixelFormat::Gray;
+        assert_eq!(eme(&pixels, format, 4, 4), eme(&pixels[..8], format, 4, 4));
+        assert_eq!(
+            ssim(&pixels, &pixels, format, 4),
+            ssim(&pixels[..8], &pixels[..8], format, 4)
+        );
+        assert_eq!(eme(&[1; 10], format, 4, 4), 0.0);
+
+        // No complete rows.
+        for width in [0, 11] {
+            assert_eq!(eme(&pixels, format, width, 4), 0.0);
+            assert_eq!(ssim(&pixels, &pixels, format, width), 1.0);
+        }
+        assert_eq!(ssim(&[1, 2, 3], &[1, 2, 3], format, 4), 1.0);
+    }
+}
@@ -0,0 +1,92 @@
+/// Layout of the channels in a pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// Grayscale (1 channel).
+    Gray,
+
+    /// Grayscale followed by alpha (2 channels).
+    GrayAlpha,
+
+    /// Red, green and blue (3 channels).
+    Rgb,
+
+    /// Red, green, blue and alpha (4 channels).
+    Rgba,
+
+    /// Blue, green and red (3 channels).
+    Bgr,
+
+    /// Blue, green, red and alpha (4 channels).
+    Bgra,
+
+    /// Alpha, red, green and blue (4 channels).
+    Argb,
+
+    /// Alpha, blue, green and red (4 channels).
+    Abgr,
+}
+
+impl PixelFormat {
+    /// Returns the number of channels in a pixel.
+    pub const fn channels(self) -> usize {
+        match self {
+            Self::Gray => 1,
+            Self::GrayAlpha => 2,
+            Self::Rgb | Self::Bgr => 3,
+            Self::Rgba | Self::Bgra | Self::Argb | Self::Abgr => 4,
+        }
+    }
+
+    /// Returns `true` if this format has no color channels.
+    pub const fn is_grayscale(self) -> bool {
+        matches!(self, Self::Gray | Self::GrayAlpha)
+    }
+
+    /// Returns the position of the alpha channel in a pixel, if any.
+    pub const fn alpha_offset(self) -> Option<usize> {
+        match self {
+            Self::GrayAlpha => Some(1),
+            Self::Rgba | Self::Bgra => Some(3),
+            Self::Argb | Self::Abgr => Some(0),
+            Self::Gray | Self::Rgb | Self::Bgr => None,
+        }
+    }
+
+    /// Returns the positions of the red, green and blue channels in a pixel.
+    ///
+    /// All the positions point to the gray channel in the case of grayscale formats.
+    pub(crate) const fn color_offsets(self) -> [usize; 3] {
+        match self {
+            Self::Gray | Self::GrayAlpha => [0, 0, 0],
+            Self::Rgb | Self::Rgba => [0, 1, 2],
+            Self::Bgr | Self::Bgra => [2, 1, 0],
+            Self::Argb => [1, 2, 3],
+            Self::Abgr => [3, 2, 1],
+        }
+    }
+
+    /// Copies the pixels of `src` having this format to `dst` having the format `dst_format`.
+    ///
+    /// Colors are converted to gray levels by `intensity`, gray levels are replicated to the color channels,
+    /// and the alpha channel is filled with `255` if `src` has no alpha channel.
+    pub(crate) fn convert<F>(self, src: &[u8], dst: &mut [u8], dst_format: Self, intensity: F)
+    where
+        F: Fn(u8, u8, u8) -> u8,
+    {
+        let [r, g, b] = self.color_offsets();
+        let [dst_r, dst_g, dst_b] = dst_format.color_offsets();
+        let pixels = src.chunks_exact(self.channels());
+        for (p, q) in pixels.zip(dst.chunks_exact_mut(dst_format.channels())) {
+            if dst_format.is_grayscale() {
+                q[dst_r] = intensity(p[r], p[g], p[b]);
+            } else {
+                q[dst_r] = p[r];
+                q[dst_g] = p[g];
+                q[dst_b] = p[b];
+            }
+            if let Some(dst_a) = dst_format.alpha_offset() {
+                q[dst_a] = self.alpha_offset().map_or(u8::MAX, |a| p[a]);
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{Image, IntensityModel, Pdf, PixelFormat};
+
+/// [`SceneChangeDetector`] detects scene changes (hard cuts) in a sequence of video frames.
+///
+/// The histograms of successive frames are compared by their Hellinger distance
+/// (derived from the Bhattacharyya coefficient), and a scene change is reported
+/// if the distance exceeds the threshold.
+#[derive(Debug, Clone)]
+pub struct SceneChangeDetector {
+    threshold: f32,
+    distance: f32,
+    prev: Option<Pdf>,
+}
+
+impl SceneChangeDetector {
+    /// Default threshold of the histogram distance.
+    pub const DEFAULT_THRESHOLD: f32 = 0.3;
+
+    /// Makes a new [`SceneChangeDetector`] instance.
+    ///
+    /// `threshold` is the histogram distance (from `0.0` to `1.0`) above which
+    /// a frame is regarded as the start of a new scene.
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold,
+            distance: 0.0,
+            prev: None,
+        }
+    }
+
+    /// Feeds an RGB frame and returns `true` if a scene change is detected.
+    pub fn detect_rgb_frame(&mut self, pixels: &[u8]) -> bool {
+        self.detect_frame(pixels, PixelFormat::Rgb)
+    }
+
+    /// Feeds an RGBA frame and returns `true` if a scene change is detected.
+    pub fn detect_rgba_frame(&mut self, pixels: &[u8]) -> bool {
+        self.detect_frame(pixels, PixelFormat::Rgba)
+    }
+
+    /// Feeds a frame having the given pixel format and returns `true` if a scene change is detected.
+    pub fn detect_frame(&mut self, pixels: &[u8], format: PixelFormat) -> bool {
+        self.detect(Pdf::new(
+            &Image::new(pixels, format),
+            IntensityModel::HsvValue,
+        ))
+    }
+
+    /// Returns the histogram distance between the last two frames.
+    pub fn last_distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Forgets the previous frame.
+    pub fn reset(&mut self) {
+        self.distance = 0.0;
+        self.prev = None;
+    }
+
+    pub(crate) fn detect(&mut self, pdf: Pdf) -> bool {
+        self.distance = self.prev.as_ref().map_or(0.0, |prev| prev.distance(&pdf));
+        self.prev = Some(pdf);
+        self.distance > self.threshold
+    }
+}
+
+impl Default for SceneChangeDetector {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_THRESHOLD)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_works() {
+        let dark = [10, 10, 10, 20, 20, 20, 30, 30, 30, 40, 40, 40];
+        let bright = [200, 200, 200, 210, 210, 210, 220, 220, 220, 230, 230, 230];
+
+        let mut detector = SceneChangeDetector::default();
+        assert!(!detector.detect_rgb_frame(&dark));
+        assert!(!detector.detect_rgb_frame(&dark));
+        assert_eq!(detector.last_distance(), 0.0);
+        assert!(detector.detect_rgb_frame(&bright));
+        assert!(!detector.detect_rgb_frame(&bright));
+    }
+}
@@ -0,0 +1,570 @@
+//! SIMD implementations of hot loops.
+//!
+//! On x86_64, AVX2 or SSE4.1 is selected at runtime.
+//! On wasm32, `simd128` is used if the crate is compiled with `-C target-feature=+simd128`.
+use crate::color_format::GainTable;
+use crate::PixelFormat;
+
+/// Stores the HSV value (i.e., `max(R, G, B)`) of each pixel in `pixels` to `values`.
+///
+/// `pixels` must be contiguous pixels of an RGB(A) format and `values` must have the same number of elements as the pixels.
+pub fn max3(pixels: &[u8], format: PixelFormat, values: &mut [u8]) {
+    let channels = format.channels();
+    debug_assert!(!format.is_grayscale());
+    debug_assert_eq!(pixels.len() / channels, values.len());
+
+    let done = max3_simd(pixels, format, values);
+    let [r, g, b] = format.color_offsets();
+    for (p, v) in pixels[done * channels..]
+        .chunks_exact(channels)
+        .zip(&mut values[done..])
+    {
+        *v = p[r].max(p[g]).max(p[b]);
+    }
+}
+
+/// Scales the color components of each pixel by the gain indexed by its HSV value
+/// (see [`GainTable`](crate::color_format::GainTable)).
+///
+/// `values` must hold the HSV values of the pixels (see [`max3()`]).
+/// Since no component exceeds the HSV value of its pixel, the products fit in 32 bits.
+pub fn scale_by_value(
+    pixels: &mut [u8],
+    format: PixelFormat,
+    values: &[u8],
+    gains: &[u32; 256],
+    black: u8,
+) {
+    let channels = format.channels();
+    debug_assert!(!format.is_grayscale());
+    debug_assert_eq!(pixels.len() / channels, values.len());
+
+    let done = scale_by_value_simd(pixels, format, values, gains, black);
+    let [r, g, b] = format.color_offsets();
+    for (p, &v) in pixels[done * channels..]
+        .chunks_exact_mut(channels)
+        .zip(&values[done..])
+    {
+        if v == 0 {
+            p[r] = black;
+            p[g] = black;
+            p[b] = black;
+            continue;
+        }
+        let gain = gains[usize::from(v)];
+        for i in [r, g, b] {
+            p[i] = ((u32::from(p[i]) * gain + ROUND) >> GainTable::SHIFT).min(255) as u8;
+        }
+    }
+}
+
+/// Rounding term of the fixed-point scaling.
+const ROUND: u32 = 1 << (GainTable::SHIFT - 1);
+
+/// Returns the shuffle mask to place the color components of 4 pixels into 4 32-bit lanes.
+///
+/// The remaining byte of each lane (the alpha channel or nothing) is set to zero.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+fn spread_mask(format: PixelFormat) -> [u8; 16] {
+    const ZERO: u8 = 0x80;
+    let mut mask = [ZERO; 16];
+    if format.channels() == 3 {
+        for pixel in 0..4 {
+            for c in 0..3 {
+                mask[pixel * 4 + c] = (pixel * 3 + c) as u8;
+            }
+        }
+    } else {
+        let [r, g, b] = format.color_offsets();
+        for pixel in 0..4 {
+            for c in [r, g, b] {
+                mask[pixel * 4 + c] = (pixel * 4 + c) as u8;
+            }
+        }
+    }
+    mask
+}
+
+/// Returns the shuffle mask to put the color components placed by [`spread_mask()`] back in their original positions,
+/// and the mask of the bytes to be kept (i.e., alpha channels and the bytes after 4 RGB pixels).
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+fn pack_masks(format: PixelFormat) -> ([u8; 16], [u8; 16]) {
+    const ZERO: u8 = 0x80;
+    let spread = spread_mask(format);
+    let mut pack = [ZERO; 16];
+    for (lane_byte, &i) in spread.iter().enumerate() {
+        if i != ZERO {
+            pack[usize::from(i)] = lane_byte as u8;
+        }
+    }
+    let keep = pack.map(|i| if i == ZERO { 0xFF } else { 0 });
+    (pack, keep)
+}
+
+/// Shuffle mask to gather the lowest bytes of 4 32-bit lanes into the first 4 bytes.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+const GATHER_MASK: [u8; 16] = [
+    0, 4, 8, 12, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+];
+
+#[cfg(target_arch = "x86_64")]
+fn max3_simd(pixels: &[u8], format: PixelFormat, values: &mut [u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: The CPU supports AVX2.
+        unsafe { x86::max3_avx2(pixels, format, values) }
+    } else if is_x86_feature_detected!("sse4.1") {
+        // SAFETY: The CPU supports SSE4.1.
+        unsafe { x86::max3_sse41(pixels, format, values) }
+    } else {
+        0
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn max3_simd(pixels: &[u8], format: PixelFormat, values: &mut [u8]) -> usize {
+    wasm::max3_simd128(pixels, format, values)
+}
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+)))]
+fn max3_simd(_pixels: &[u8], _format: PixelFormat, _values: &mut [u8]) -> usize {
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+fn scale_by_value_simd(
+    pixels: &mut [u8],
+    format: PixelFormat,
+    values: &[u8],
+    gains: &[u32; 256],
+    black: u8,
+) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: The CPU supports AVX2.
+        unsafe { x86::scale_by_value_avx2(pixels, format, values, gains, black) }
+    } else if is_x86_feature_detected!("sse4.1") {
+        // SAFETY: The CPU supports SSE4.1.
+        unsafe { x86::scale_by_value_sse41(pixels, format, values, gains, black) }
+    } else {
+        0
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn scale_by_value_simd(
+    pixels: &mut [u8],
+    format: PixelFormat,
+    values: &[u8],
+    gains: &[u32; 256],
+    black: u8,
+) -> usize {
+    wasm::scale_by_value_simd128(pixels, format, values, gains, black)
+}
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+)))]
+fn scale_by_value_simd(
+    _pixels: &mut [u8],
+    _format: PixelFormat,
+    _values: &[u8],
+    _gains: &[u32; 256],
+    _black: u8,
+) -> usize {
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{pack_masks, spread_mask, GATHER_MASK, ROUND};
+    use crate::color_format::GainTable;
+    use crate::PixelFormat;
+    use std::arch::x86_64::*;
+
+    const SHIFT: i32 = GainTable::SHIFT as i32;
+
+    /// Returns the number of the processed pixels.
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn max3_sse41(pixels: &[u8], format: PixelFormat, values: &mut [u8]) -> usize {
+        let channels = format.channels();
+        let spread = _mm_loadu_si128(spread_mask(format).as_ptr().cast());
+        let gather = _mm_loadu_si128(GATHER_MASK.as_ptr().cast());
+
+        let mut i = 0;
+        while i + 4 <= values.len() && i * channels + 16 <= pixels.len() {
+            let x = _mm_loadu_si128(pixels.as_ptr().add(i * channels).cast());
+            let x = _mm_shuffle_epi8(x, spread);
+            let m = _mm_max_epu8(x, _mm_srli_epi32::<8>(x));
+            let m = _mm_max_epu8(m, _mm_srli_epi32::<16>(m));
+            let v = _mm_cvtsi128_si32(_mm_shuffle_epi8(m, gather));
+            values[i..i + 4].copy_from_slice(&v.to_le_bytes());
+            i += 4;
+        }
+        i
+    }
+
+    /// Returns the number of the processed pixels.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn max3_avx2(pixels: &[u8], format: PixelFormat, values: &mut [u8]) -> usize {
+        let channels = format.channels();
+        let spread =
+            _mm256_broadcastsi128_si256(_mm_loadu_si128(spread_mask(format).as_ptr().cast()));
+        let gather = _mm256_broadcastsi128_si256(_mm_loadu_si128(GATHER_MASK.as_ptr().cast()));
+        let lanes = _mm256_setr_epi32(0, 4, 1, 1, 1, 1, 1, 1);
+
+        let mut i = 0;
+        while i + 8 <= values.len() && i * channels + 4 * channels + 16 <= pixels.len() {
+            let p = pixels.as_ptr().add(i * channels);
+            let lo = _mm_loadu_si128(p.cast());
+            let hi = _mm_loadu_si128(p.add(4 * channels).cast());
+            let x = _mm256_shuffle_epi8(_mm256_set_m128i(hi, lo), spread);
+            let m = _mm256_max_epu8(x, _mm256_srli_epi32::<8>(x));
+            let m = _mm256_max_epu8(m, _mm256_srli_epi32::<16>(m));
+            let m = _mm256_permutevar8x32_epi32(_mm256_shuffle_epi8(m, gather), lanes);
+            _mm_storel_epi64(values.as_mut_ptr().add(i).cast(), _mm256_castsi256_si128(m));
+            i += 8;
+        }
+        i
+    }
+
+    /// Returns the number of the processed pixels.
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn scale_by_value_sse41(
+        pixels: &mut [u8],
+        format: PixelFormat,
+        values: &[u8],
+        gains: &[u32; 256],
+        black: u8,
+    ) -> usize {
+        let channels = format.channels();
+        let (pack, keep) = pack_masks(format);
+        let spread = _mm_loadu_si128(spread_mask(format).as_ptr().cast());
+        let pack = _mm_loadu_si128(pack.as_ptr().cast());
+        let keep = _mm_loadu_si128(keep.as_ptr().cast());
+        let black = _mm_set1_epi32(i32::from(black));
+
+        let mut i = 0;
+        while i + 4 <= values.len() && i * channels + 16 <= pixels.len() {
+            let p = pixels.as_mut_ptr().add(i * channels);
+            let x = _mm_loadu_si128(p.cast());
+            let v = &values[i..i + 4];
+            let gain = _mm_setr_epi32(
+                gains[usize::from(v[0])] as i32,
+                gains[usize::from(v[1])] as i32,
+                gains[usize::from(v[2])] as i32,
+                gains[usize::from(v[3])] as i32,
+            );
+            let v = _mm_cvtepu8_epi32(_mm_cvtsi32_si128(i32::from_le_bytes([
+                v[0], v[1], v[2], v[3],
+            ])));
+            let floor = _mm_and_si128(_mm_cmpeq_epi32(v, _mm_setzero_si128()), black);
+            let y = scale_lanes_sse41(_mm_shuffle_epi8(x, spread), gain, floor);
+            let y = _mm_or_si128(_mm_shuffle_epi8(y, pack), _mm_and_si128(x, keep));
+            _mm_storeu_si128(p.cast(), y);
+            i += 4;
+        }
+        i
+    }
+
+    /// Scales each byte of the 32-bit lanes by the gain of the lane, raising the results to `floor`.
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn scale_lanes_sse41(x: __m128i, gain: __m128i, floor: __m128i) -> __m128i {
+        let round = _mm_set1_epi32(ROUND as i32);
+        let max = _mm_set1_epi32(255);
+        let mut y = _mm_setzero_si128();
+        for byte in 0..4 {
+            let shift = _mm_cvtsi32_si128(byte * 8);
+            let c = _mm_and_si128(_mm_srl_epi32(x, shift), max);
+            let c = _mm_srli_epi32::<SHIFT>(_mm_add_epi32(_mm_mullo_epi32(c, gain), round));
+            let c = _mm_max_epu32(_mm_min_epu32(c, max), floor);
+            y = _mm_or_si128(y, _mm_sll_epi32(c, shift));
+        }
+        y
+    }
+
+    /// Returns the number of the processed pixels.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn scale_by_value_avx2(
+        pixels: &mut [u8],
+        format: PixelFormat,
+        values: &[u8],
+        gains: &[u32; 256],
+        black: u8,
+    ) -> usize {
+        let channels = format.channels();
+        let (pack, keep) = pack_masks(format);
+        let spread =
+            _mm256_broadcastsi128_si256(_mm_loadu_si128(spread_mask(format).as_ptr().cast()));
+        let pack = _mm256_broadcastsi128_si256(_mm_loadu_si128(pack.as_ptr().cast()));
+        let keep = _mm256_broadcastsi128_si256(_mm_loadu_si128(keep.as_ptr().cast()));
+        let black = _mm256_set1_epi32(i32::from(black));
+        let round = _mm256_set1_epi32(ROUND as i32);
+        let max = _mm256_set1_epi32(255);
+
+        let mut i = 0;
+        while i + 8 <= values.len() && i * channels + 4 * channels + 16 <= pixels.len() {
+            let p = pixels.as_mut_ptr().add(i * channels);
+            let lo = _mm_loadu_si128(p.cast());
+            let hi = _mm_loadu_si128(p.add(4 * channels).cast());
+            let x = _mm256_set_m128i(hi, lo);
+            let v = _mm256_cvtepu8_epi32(_mm_loadl_epi64(values.as_ptr().add(i).cast()));
+            let gain = _mm256_i32gather_epi32::<4>(gains.as_ptr().cast(), v);
+            let floor = _mm256_and_si256(_mm256_cmpeq_epi32(v, _mm256_setzero_si256()), black);
+
+            let spread_x = _mm256_shuffle_epi8(x, spread);
+            let mut y = _mm256_setzero_si256();
+            for byte in 0..4 {
+                let shift = _mm_cvtsi32_si128(byte * 8);
+                let c = _mm256_and_si256(_mm256_srl_epi32(spread_x, shift), max);
+                let c = _mm256_srli_epi32::<SHIFT>(_mm256_add_epi32(
+                    _mm256_mullo_epi32(c, gain),
+                    round,
+                ));
+                let c = _mm256_max_epu32(_mm256_min_epu32(c, max), floor);
+                y = _mm256_or_si256(y, _mm256_sll_epi32(c, shift));
+            }
+            let y = _mm256_or_si256(_mm256_shuffle_epi8(y, pack), _mm256_and_si256(x, keep));
+
+            // The lower half is stored first, because the bytes it keeps may be overwritten by the upper half.
+            _mm_storeu_si128(p.cast(), _mm256_castsi256_si128(y));
+            _mm_storeu_si128(p.add(4 * channels).cast(), _mm256_extracti128_si256::<1>(y));
+            i += 8;
+        }
+        i
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm {
+    use super::{pack_masks, spread_mask, GATHER_MASK, ROUND};
+    use crate::color_format::GainTable;
+    use crate::PixelFormat;
+    use std::arch::wasm32::*;
+
+    /// Returns the number of the processed pixels.
+    pub fn max3_simd128(pixels: &[u8], format: PixelFormat, values: &mut [u8]) -> usize {
+        let channels = format.channels();
+        // SAFETY: The masks are 16-byte arrays.
+        let spread = unsafe { v128_load(spread_mask(format).as_ptr().cast()) };
+        let gather = unsafe { v128_load(GATHER_MASK.as_ptr().cast()) };
+
+        let mut i = 0;
+        while i + 4 <= values.len() && i * channels + 16 <= pixels.len() {
+            // SAFETY: `pixels` has at least 16 bytes from the offset.
+            let x = unsafe { v128_load(pixels.as_ptr().add(i * channels).cast()) };
+            let x = i8x16_swizzle(x, spread);
+            let m = u8x16_max(x, u32x4_shr(x, 8));
+            let m = u8x16_max(m, u32x4_shr(m, 16));
+            let v = i32x4_extract_lane::<0>(i8x16_swizzle(m, gather));
+            values[i..i + 4].copy_from_slice(&v.to_le_bytes());
+            i += 4;
+        }
+        i
+    }
+
+    /// Returns the number of the processed pixels.
+    pub fn scale_by_value_simd128(
+        pixels: &mut [u8],
+        format: PixelFormat,
+        values: &[u8],
+        gains: &[u32; 256],
+        black: u8,
+    ) -> usize {
+        let channels = format.channels();
+        let (pack, keep) = pack_masks(format);
+        // SAFETY: The masks are 16-byte arrays.
+        let spread = unsafe { v128_load(spread_mask(format).as_ptr().cast()) };
+        let pack = unsafe { v128_load(pack.as_ptr().cast()) };
+        let keep = unsafe { v128_load(keep.as_ptr().cast()) };
+        let black = u32x4_splat(u32::from(black));
+        let round = u32x4_splat(ROUND);
+        let max = u32x4_splat(255);
+
+        let mut i = 0;
+        while i + 4 <= values.len() && i * channels + 16 <= pixels.len() {
+            // SAFETY: `pixels` has at least 16 bytes from the offset.
+            let p = unsafe { pixels.as_mut_ptr().add(i * channels) };
+            let x = unsafe { v128_load(p.cast()) };
+            let v = &values[i..i + 4];
+            let gain = u32x4(
+                gains[usize::from(v[0])],
+                gains[usize::from(v[1])],
+                gains[usize::from(v[2])],
+                gains[usize::from(v[3])],
+            );
+            let v = u32x4(
+                u32::from(v[0]),
+                u32::from(v[1]),
+                u32::from(v[2]),
+                u32::from(v[3]),
+            );
+            let floor = v128_and(u32x4_eq(v, u32x4_splat(0)), black);
+
+            let spread_x = i8x16_swizzle(x, spread);
+            let mut y = u32x4_splat(0);
+            for byte in 0..4 {
+                let c = v128_and(u32x4_shr(spread_x, byte * 8), max);
+                let c = u32x4_shr(i32x4_add(i32x4_mul(c, gain), round), GainTable::SHIFT);
+                let c = u32x4_max(u32x4_min(c, max), floor);
+                y = v128_or(y, i32x4_shl(c, byte * 8));
+            }
+            let y = v128_or(i8x16_swizzle(y, pack), v128_and(x, keep));
+            // SAFETY: `pixels` has at least 16 bytes from the offset.
+            unsafe { v128_store(p.cast(), y) };
+            i += 4;
+        }
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [PixelFormat; 6] = [
+        PixelFormat::Rgb,
+        PixelFormat::Bgr,
+        PixelFormat::Rgba,
+        PixelFormat::Bgra,
+        PixelFormat::Argb,
+        PixelFormat::Abgr,
+    ];
+
+    fn max3_scalar(pixels: &[u8], format: PixelFormat) -> Vec<u8> {
+        let [r, g, b] = format.color_offsets();
+        pixels
+            .chunks_exact(format.channels())
+            .map(|p| p[r].max(p[g]).max(p[b]))
+            .collect()
+    }
+
+    #[test]
+    fn max3_works() {
+        let pixels = (0..1000u32)
+            .map(|i| (i * 37 % 256) as u8)
+            .collect::<Vec<_>>();
+        for format in FORMATS {
+            for len in [0, 1, 5, 17, 63, 1000] {
+                let pixels = &pixels[..len / format.channels() * format.channels()];
+                let mut values = vec![0; pixels.len() / format.channels()];
+                max3(pixels, format, &mut values);
+                assert_eq!(values, max3_scalar(pixels, format), "{format:?}");
+            }
+        }
+    }
+
+    /// Curves having various shapes (the last one raises black pixels).
+    fn curves() -> Vec<[u8; 256]> {
+        vec![
+            std::array::from_fn(|i| i as u8),
+            std::array::from_fn(|i| (255.0 * (i as f32 / 255.0).sqrt()).round() as u8),
+            std::array::from_fn(|i| (i / 2) as u8),
+            std::array::from_fn(|i| (40 + i * 215 / 255) as u8),
+        ]
+    }
+
+    fn scale_by_value_scalar(pixels: &[u8], format: PixelFormat, gains: &GainTable) -> Vec<u8> {
+        let [r, g, b] = format.color_offsets();
+        let mut pixels = pixels.to_vec();
+        for p in pixels.chunks_exact_mut(format.channels()) {
+            let v = p[r].max(p[g]).max(p[b]);
+            (p[r], p[g], p[b]) = gains.scale_rgb(p[r], p[g], p[b], v);
+        }
+        pixels
+    }
+
+    fn test_pixels() -> Vec<u8> {
+        // Include black pixels, which are raised to `curve[0]`.
+        (0..1000u32)
+            .map(|i| if i % 50 < 4 { 0 } else { (i * 37 % 256) as u8 })
+            .collect()
+    }
+
+    #[test]
+    fn scale_by_value_works() {
+        let pixels = test_pixels();
+        for curve in curves() {
+            let gains = GainTable::new(&curve);
+            for format in FORMATS {
+                for len in [0, 1, 5, 17, 63, 1000] {
+                    let original = &pixels[..len / format.channels() * format.channels()];
+                    let mut scaled = original.to_vec();
+                    gains.scale_by_value(&mut scaled, format);
+                    assert_eq!(
+                        scaled,
+                        scale_by_value_scalar(original, format, &gains),
+                        "{format:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn x86_scale_implementations_work() {
+        type Kernel = unsafe fn(&mut [u8], PixelFormat, &[u8], &[u32; 256], u8) -> usize;
+        let mut kernels: Vec<Kernel> = Vec::new();
+        if is_x86_feature_detected!("sse4.1") {
+            kernels.push(x86::scale_by_value_sse41);
+        }
+        if is_x86_feature_detected!("avx2") {
+            kernels.push(x86::scale_by_value_avx2);
+        }
+
+        let pixels = test_pixels();
+        for curve in curves() {
+            let gains = GainTable::new(&curve);
+            for format in FORMATS {
+                let original = &pixels[..pixels.len() / format.channels() * format.channels()];
+                let expected = scale_by_value_scalar(original, format, &gains);
+                let values = max3_scalar(original, format);
+                for &kernel in &kernels {
+                    let mut scaled = original.to_vec();
+                    let done =
+                        unsafe { kernel(&mut scaled, format, &values, &gains.gains, gains.black) };
+                    assert!(done > 0);
+                    let len = done * format.channels();
+                    assert_eq!(scaled[..len], expected[..len], "{format:?}");
+                    assert_eq!(scaled[len..], original[len..], "{format:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn x86_implementations_work() {
+        let pixels = (0..1000u32)
+            .map(|i| (i * 37 % 256) as u8)
+            .collect::<Vec<_>>();
+        for format in FORMATS {
+            let pixels = &pixels[..pixels.len() / format.channels() * format.channels()];
+            let expected = max3_scalar(pixels, format);
+            if is_x86_feature_detected!("sse4.1") {
+                let mut values = vec![0; expected.len()];
+                let done = unsafe { x86::max3_sse41(pixels, format, &mut values) };
+                assert!(done > 0);
+                assert_eq!(values[..done], expected[..done], "{format:?}");
+            }
+            if is_x86_feature_detected!("avx2") {
+                let mut values = vec![0; expected.len()];
+                let done = unsafe { x86::max3_avx2(pixels, format, &mut values) };
+                assert!(done > 0);
+                assert_eq!(values[..done], expected[..done], "{format:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+/// Variant of the AGCWD algorithm.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AgcwdVariant {
+    /// The original AGCWD algorithm.
+    ///
+    /// As the gamma values are never greater than `1.0`, this always brightens an image
+    /// (even if it is already bright or washed out).
+    #[default]
+    Original,
+
+    /// The improved AGCWD algorithm (IAGCWD) described in the paper
+    /// "Contrast Enhancement of Brightness-Distorted Images by Improved Adaptive Gamma Correction" (Cao et al., 2018).
+    ///
+    /// An image is classified by its mean intensity `m` (in the range from `0` to `255`) as follows:
+    /// - Dimmed (`m < 112 * 0.7`): the gamma values are truncated to be at least `0.5` to prevent over-enhancement.
+    /// - Bright (`m > 112 * 1.3`): the negative image is enhanced and then inverted back, which darkens the image.
+    /// - Otherwise: the image is left unchanged.
+    ///
+    /// [`AgcwdOptions::alpha`](crate::AgcwdOptions::alpha) is ignored and the values from the paper
+    /// (`0.75` for dimmed images and `0.25` for bright images) are used instead.
+    Improved,
+}
+
+impl AgcwdVariant {
+    /// Mean intensity (normalized to the range from `0.0` to `1.0`) regarded as neither dimmed nor bright.
+    pub(crate) const IMPROVED_MEAN: f32 = 112.0 / 255.0;
+
+    /// Relative difference from [`AgcwdVariant::IMPROVED_MEAN`] to classify an image as dimmed or bright.
+    pub(crate) const IMPROVED_THRESHOLD: f32 = 0.3;
+
+    pub(crate) const IMPROVED_DIMMED_ALPHA: f32 = 0.75;
+    pub(crate) const IMPROVED_BRIGHT_ALPHA: f32 = 0.25;
+
+    /// Minimum gamma value for dimmed images.
+    pub(crate) const IMPROVED_MIN_GAMMA: f32 = 0.5;
+}
@@ -0,0 +1,253 @@
+use crate::{Agcwd, AgcwdError, AgcwdOptions, Image, Pdf, PixelFormat, SceneChangeDetector};
+
+/// [`AgcwdVideo`] options.
+#[derive(Debug, Clone)]
+pub struct AgcwdVideoOptions {
+    /// Options of the underlying AGCWD algorithm.
+    pub agcwd: AgcwdOptions,
+
+    /// Decay rate of the exponentially smoothed histogram.
+    ///
+    /// The histogram used to enhance a frame is `decay * previous + (1.0 - decay) * current`.
+    /// `0.0` disables the smoothing and larger values (up to `1.0`) make the enhancement more stable.
+    ///
+    /// Defaults to `0.9`.
+    pub decay: f32,
+
+    /// Histogram distance threshold to detect scene changes (see [`SceneChangeDetector`]).
+    ///
+    /// When a scene change is detected, the smoothed histogram is reset to that of the current frame.
+    /// `None` disables the detection.
+    ///
+    /// Defaults to `Some(SceneChangeDetector::DEFAULT_THRESHOLD)`.
+    pub scene_change_threshold: Option<f32>,
+}
+
+impl Default for AgcwdVideoOptions {
+    fn default() -> Self {
+        Self {
+            agcwd: AgcwdOptions::default(),
+            decay: 0.9,
+            scene_change_threshold: Some(SceneChangeDetector::DEFAULT_THRESHOLD),
+        }
+    }
+}
+
+impl AgcwdVideoOptions {
+    /// Checks whether the options have valid values.
+    pub fn validate(&self) -> Result<(), AgcwdError> {
+        self.agcwd.validate()?;
+        if !(0.0..=1.0).contains(&self.decay) {
+            return Err(AgcwdError::InvalidOption {
+                name: "decay",
+                value: self.decay,
+            });
+        }
+        if let Some(threshold) = self.scene_change_threshold {
+            if !(threshold.is_finite() && threshold >= 0.0) {
+                return Err(AgcwdError::InvalidOption {
+                    name: "scene_change_threshold",
+                    value: threshold,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// [`AgcwdVideo`] enhances a sequence of video frames.
+///
+/// Unlike [`Agcwd`], this keeps an exponentially smoothed histogram across frames
+/// to prevent the enhanced frames from flickering.
+#[derive(Debug)]
+pub struct AgcwdVideo {
+    agcwd: Agcwd,
+    decay: f32,
+    pdf: Option<Pdf>,
+    detector: Option<SceneChangeDetector>,
+}
+
+impl AgcwdVideo {
+    /// Makes a new [`AgcwdVideo`] instance with the default options.
+    pub fn new() -> Self {
+        Self::with_options(Default::default())
+    }
+
+    /// Makes a new [`AgcwdVideo`] instance with the given options.
+    pub fn with_options(options: AgcwdVideoOptions) -> Self {
+        Self {
+            agcwd: Agcwd::with_options(options.agcwd),
+            decay: options.decay,
+            pdf: None,
+            detector: options.scene_change_threshold.map(SceneChangeDetector::new),
+        }
+    }
+
+    /// Makes a new [`AgcwdVideo`] instance with the given options, returning an error if they are invalid.
+    pub fn try_with_options(options: AgcwdVideoOptions) -> Result<Self, AgcwdError> {
+        options.validate()?;
+        Ok(Self::with_options(options))
+    }
+
+    /// Enhances the contrast of an RGB frame.
+    pub fn enhance_rgb_frame(&mut self, pixels: &mut [u8]) {
+        self.enhance_frame(pixels, PixelFormat::Rgb);
+    }
+
+    /// Enhances the contrast of an RGBA frame.
+    pub fn enhance_rgba_frame(&mut self, pixels: &mut [u8]) {
+        self.enhance_frame(pixels, PixelFormat::Rgba);
+    }
+
+    /// Discards the histogram accumulated from the previous frames.
+    pub fn reset(&mut self) {
+        self.pdf = None;
+        if let Some(detector) = &mut self.detector {
+            detector.reset();
+        }
+    }
+
+    /// Enhances the contrast of a frame having the given pixel format.
+    pub fn enhance_frame(&mut self, pixels: &mut [u8], format: PixelFormat) {
+        let model = self.agcwd.options.intensity_model;
+        let current = Pdf::new(&Image::new(pixels, format), model);
+        if let Some(detector) = &mut self.detector {
+            if detector.detect(current.clone()) {
+                self.pdf = None;
+            }
+        }
+        let pdf = match &mut self.pdf {
+            Some(pdf) => {
+                pdf.blend(&current, self.decay);
+                pdf
+            }
+            None => self.pdf.insert(current),
+        };
+        let curve = self.agcwd.curve_from_pdf(pdf);
+        curve.apply_image(pixels, format);
+    }
+
+    /// Enhances the contrast of a frame having the given pixel format, returning an error if the input is invalid.
+    ///
+    /// See [`Agcwd::try_enhance_image()`] for the possible errors.
+    /// The accumulated histogram is left unchanged on failure.
+    pub fn try_enhance_frame(
+        &mut self,
+        pixels: &mut [u8],
+        format: PixelFormat,
+    ) -> Result<(), AgcwdError> {
+        self.agcwd.check_input(pixels, format)?;
+        self.enhance_frame(pixels, format);
+        Ok(())
+    }
+}
+
+impl Default for AgcwdVideo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_frame_is_enhanced_as_a_still_image() {
+        let original = [1, 2, 3, 40, 50, 60, 200, 100, 0];
+
+        let mut frame = original;
+        let mut video = AgcwdVideo::new();
+        video.enhance_rgb_frame(&mut frame);
+
+        let mut image = original;
+        Agcwd::new().enhance_rgb_image(&mut image);
+        assert_eq!(frame, image);
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let options = AgcwdVideoOptions {
+            decay: f32::NAN,
+            ..Default::default()
+        };
+        assert!(AgcwdVideo::try_with_options(options).is_err());
+        let options = AgcwdVideoOptions {
+            scene_change_threshold: Some(-1.0),
+            ..Default::default()
+        };
+        assert!(AgcwdVideo::try_with_options(options).is_err());
+
+        let mut video = AgcwdVideo::new();
+        assert_eq!(
+            video.try_enhance_frame(&mut [], PixelFormat::Rgb),
+            Err(AgcwdError::EmptyImage)
+        );
+        video
+            .try_enhance_frame(&mut [1, 2, 3], PixelFormat::Rgb)
+            .unwrap();
+    }
+
+    #[test]
+    fn histogram_is_smoothed_across_frames() {
+        let dark = [10, 10, 10, 20, 20, 20, 30, 30, 30, 40, 40, 40];
+        let bright = [200, 200, 200, 210, 210, 210, 220, 220, 220, 230, 230, 230];
+
+        let mut video = AgcwdVideo::with_options(AgcwdVideoOptions {
+            scene_change_threshold: None,
+            ..Default::default()
+        });
+        video.enhance_rgb_frame(&mut dark.clone());
+
+        let mut smoothed = bright;
+        video.enhance_rgb_frame(&mut smoothed);
+
+        let mut unsmoothed = bright;
+        Agcwd::new().enhance_rgb_image(&mut unsmoothed);
+        assert_ne!(smoothed, unsmoothed);
+
+        video.reset();
+        let mut frame = bright;
+        video.enhance_rgb_frame(&mut frame);
+        assert_eq!(frame, unsmoothed);
+    }
+
+    #[test]
+    fn default_instance_smooths_histogram() {
+        // The second frame differs too little from the first to be regarded as a scene change.
+        let first = (0..32).flat_map(|i| [10 + i; 3]).collect::<Vec<u8>>();
+        let mut second = first.clone();
+        second[..3].fill(120);
+
+        let mut default = AgcwdVideo::default();
+        let mut new = AgcwdVideo::new();
+        default.enhance_rgb_frame(&mut first.clone());
+        new.enhance_rgb_frame(&mut first.clone());
+
+        let mut frame = second.clone();
+        default.enhance_rgb_frame(&mut frame);
+        let mut expected = second.clone();
+        new.enhance_rgb_frame(&mut expected);
+        assert_eq!(frame, expected);
+
+        let mut still = second;
+        Agcwd::new().enhance_rgb_image(&mut still);
+        assert_ne!(frame, still);
+    }
+
+    #[test]
+    fn histogram_is_reset_on_scene_change() {
+        let dark = [10, 10, 10, 20, 20, 20, 30, 30, 30, 40, 40, 40];
+        let bright = [200, 200, 200, 210, 210, 210, 220, 220, 220, 230, 230, 230];
+
+        let mut video = AgcwdVideo::new();
+        video.enhance_rgb_frame(&mut dark.clone());
+
+        let mut frame = bright;
+        video.enhance_rgb_frame(&mut frame);
+
+        let mut image = bright;
+        Agcwd::new().enhance_rgb_image(&mut image);
+        assert_eq!(frame, image);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{AgcwdError, ImageView, PixelFormat};
+
+/// Layout of a YUV image.
+///
+/// Only the luminance (Y) samples are used to enhance a YUV image, and the chrominance samples are left untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvFormat {
+    /// Planar 4:2:0 (the Y plane followed by the U and V planes).
+    I420,
+
+    /// Planar 4:2:0 (the Y plane followed by the V and U planes).
+    Yv12,
+
+    /// Semi-planar 4:2:0 (the Y plane followed by the interleaved UV plane).
+    Nv12,
+
+    /// Semi-planar 4:2:0 (the Y plane followed by the interleaved VU plane).
+    Nv21,
+
+    /// Packed 4:2:2 (`Y0 U Y1 V` for each pair of pixels).
+    Yuy2,
+}
+
+impl YuvFormat {
+    /// Returns the number of bytes of a `width` x `height` image.
+    pub const fn frame_len(self, width: usize, height: usize) -> usize {
+        match self {
+            Self::I420 | Self::Yv12 | Self::Nv12 | Self::Nv21 => {
+                width * height + width.div_ceil(2) * height.div_ceil(2) * 2
+            }
+            Self::Yuy2 => width.div_ceil(2) * height * 4,
+        }
+    }
+
+    pub(crate) fn luma_view<B: AsRef<[u8]>>(
+        self,
+        pixels: B,
+        width: usize,
+        height: usize,
+    ) -> ImageView<B> {
+        self.try_luma_view(pixels, width, height)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub(crate) fn try_luma_view<B: AsRef<[u8]>>(
+        self,
+        pixels: B,
+        width: usize,
+        height: usize,
+    ) -> Result<ImageView<B>, AgcwdError> {
+        let len = pixels.as_ref().len();
+        let required = self.frame_len(width, height);
+        if len < required {
+            return Err(AgcwdError::BufferTooShort { len, required });
+        }
+        match self {
+            Self::I420 | Self::Yv12 | Self::Nv12 | Self::Nv21 => {
+                ImageView::try_new(pixels, PixelFormat::Gray, width, height, width)
+            }
+            // Each pair of a Y sample and a chroma sample is regarded as a gray+alpha pixel.
+            Self::Yuy2 => ImageView::try_new(
+                pixels,
+                PixelFormat::GrayAlpha,
+                width,
+                height,
+                width.div_ceil(2) * 4,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agcwd;
+
+    #[test]
+    fn chroma_is_left_untouched() {
+        let luma = [10, 20, 30, 40, 50, 60, 70, 80];
+        let agcwd = Agcwd::new();
+
+        let mut expected = luma;
+        agcwd.enhance_gray_image(&mut expected);
+
+        // 4x2 NV12 image.
+        let mut pixels = luma.to_vec();
+        pixels.extend_from_slice(&[128, 129, 130, 131]);
+        agcwd.enhance_yuv_image(&mut pixels, YuvFormat::Nv12, 4, 2);
+        assert_eq!(pixels[..8], expected);
+        assert_eq!(pixels[8..], [128, 129, 130, 131]);
+
+        // 4x2 YUY2 image.
+        let mut pixels = luma
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &y)| [y, 128 + i as u8])
+            .collect::<Vec<_>>();
+        agcwd.enhance_yuv_image(&mut pixels, YuvFormat::Yuy2, 4, 2);
+        for (i, p) in pixels.chunks_exact(2).enumerate() {
+            assert_eq!(p, [expected[i], 128 + i as u8]);
+        }
+    }
+}